@@ -1,13 +1,162 @@
+use eyre::{eyre, Context, Result};
 use log::info;
+use std::{
+    env,
+    fs::OpenOptions,
+    io::Write,
+    process,
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
 
-pub fn set_output(name: &str, value: &str) {
-    info!("::set-output name={}::{}", name, value);
+/// Set an output for the step
+///
+/// The output is written to the file in `GITHUB_OUTPUT`, or with the deprecated `set-output`
+/// command when that file is not available.
+pub fn set_output(name: &str, value: &str) -> Result<()> {
+    match command_file("GITHUB_OUTPUT") {
+        Some(path) => append_to_command_file(&path, name, value),
+        None => {
+            info!(
+                "::set-output name={}::{}",
+                name,
+                escape_command_value(value)
+            );
+            Ok(())
+        }
+    }
 }
 
+/// Set an environment variable for the following steps of the job
+///
+/// The variable is written to the file in `GITHUB_ENV`. It is an error when that file is not
+/// available, since the `set-env` command that used to replace it is disabled on runners.
+pub fn export_variable(name: &str, value: &str) -> Result<()> {
+    match command_file("GITHUB_ENV") {
+        Some(path) => append_to_command_file(&path, name, value),
+        None => Err(eyre!(
+            "GITHUB_ENV is not set, could not export the '{}' environment variable",
+            name
+        )),
+    }
+}
+
+/// Append Markdown to the summary of the step, shown on the workflow run's page
+///
+/// Nothing is written when `GITHUB_STEP_SUMMARY` is not available, since there is no other way to
+/// set the summary.
+pub fn append_step_summary(markdown: &str) -> Result<()> {
+    let path = match command_file("GITHUB_STEP_SUMMARY") {
+        Some(path) => path,
+        None => {
+            info!("GITHUB_STEP_SUMMARY is not set, not writing the job summary");
+            return Ok(());
+        }
+    };
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(markdown.as_bytes()))
+        .wrap_err(format!("Could not write the job summary to '{}'", path))
+}
+
+/// Whether a group of log lines is open, so it can be closed when an error interrupts it
+static GROUP_OPEN: AtomicBool = AtomicBool::new(false);
+
 pub fn group_lines(name: &str) {
+    GROUP_OPEN.store(true, Ordering::SeqCst);
     info!("::group::{}", name);
 }
 
+/// Close the open group of log lines, if there is one
 pub fn close_group() {
-    info!("::endgroup::");
+    if GROUP_OPEN.swap(false, Ordering::SeqCst) {
+        info!("::endgroup::");
+    }
+}
+
+fn command_file(variable: &str) -> Option<String> {
+    env::var(variable).ok().filter(|path| !path.is_empty())
+}
+
+/// Append `name=value` to a command file, using a heredoc delimiter for multi-line values
+fn append_to_command_file(path: &str, name: &str, value: &str) -> Result<()> {
+    let entry = if value.contains('\n') || value.contains('\r') {
+        let delimiter = heredoc_delimiter(value);
+        format!("{}<<{}\n{}\n{}\n", name, delimiter, value, delimiter)
+    } else {
+        format!("{}={}\n", name, value)
+    };
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(entry.as_bytes()))
+        .wrap_err(format!("Could not write '{}' to '{}'", name, path))
+}
+
+/// Find a delimiter that does not appear in the value
+fn heredoc_delimiter(value: &str) -> String {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    (0..)
+        .map(|attempt| format!("ghadelimiter_{}_{}_{}", process::id(), seed, attempt))
+        .find(|delimiter| !value.contains(delimiter.as_str()))
+        .unwrap()
+}
+
+/// Escape a value so it can be used in a workflow command (including multi-line values)
+pub fn escape_command_value(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn exporting_a_variable_without_github_env_fails() {
+        env::remove_var("GITHUB_ENV");
+
+        assert!(export_variable("PR_BUMP_NEXT_VERSION", "1.2.3").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn exporting_a_variable_appends_to_github_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("env");
+        env::set_var("GITHUB_ENV", &path);
+
+        export_variable("PR_BUMP_NEXT_VERSION", "1.2.3").unwrap();
+        env::remove_var("GITHUB_ENV");
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "PR_BUMP_NEXT_VERSION=1.2.3\n"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn a_group_is_closed_once() {
+        group_lines("Group");
+        assert!(GROUP_OPEN.load(Ordering::SeqCst));
+
+        close_group();
+        assert!(!GROUP_OPEN.load(Ordering::SeqCst));
+        close_group();
+        assert!(!GROUP_OPEN.load(Ordering::SeqCst));
+    }
 }
@@ -0,0 +1,172 @@
+use semver::Version;
+use serde::Serialize;
+use std::fmt::{self, Display};
+
+type Label = String;
+
+/// How much a version is bumped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpLevel {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Display for BumpLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            BumpLevel::Patch => "patch",
+            BumpLevel::Minor => "minor",
+            BumpLevel::Major => "major",
+        };
+        write!(f, "{}", level)
+    }
+}
+
+/// Something on a pull request that asks for a version bump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum Contribution {
+    /// A label that is in a category
+    Label { label: Label, level: BumpLevel },
+    /// A Conventional Commit title (or a `BREAKING CHANGE:` footer in the body)
+    Title {
+        commit_type: String,
+        breaking: bool,
+        level: BumpLevel,
+    },
+    /// A label that graduates the version from initial development to `1.0.0`
+    Graduation { label: Label },
+}
+
+impl Display for Contribution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Contribution::Label { label, level } => write!(f, "label '{}' ({})", label, level),
+            Contribution::Title {
+                commit_type,
+                breaking: true,
+                ..
+            } => write!(f, "breaking '{}' title (major)", commit_type),
+            Contribution::Title {
+                commit_type, level, ..
+            } => write!(f, "'{}' title ({})", commit_type, level),
+            Contribution::Graduation { label } => write!(f, "label '{}' (graduation)", label),
+        }
+    }
+}
+
+/// What a pull request contributed to the next version
+#[derive(Debug, Clone, Serialize)]
+pub struct PullDecision {
+    pub number: u64,
+    pub title: String,
+    pub url: Option<String>,
+    pub labels: Vec<Label>,
+    pub contributions: Vec<Contribution>,
+
+    /// The highest level the pull request asks for, or `None` when it does not ask for a bump
+    pub level: Option<BumpLevel>,
+
+    /// Why the pull request does not bump the version, when it does not
+    pub ignored_because: Option<String>,
+}
+
+/// Why the next version is what it is, from every pull request that was considered
+#[derive(Debug, Clone, Serialize)]
+pub struct BumpDecision {
+    pub current_version: Version,
+    pub next_version: Version,
+
+    /// The highest level any pull request asks for
+    pub requested_level: Option<BumpLevel>,
+
+    /// The level that was applied, after the initial development rules
+    pub level: Option<BumpLevel>,
+    pub pulls: Vec<PullDecision>,
+
+    /// Rules that changed the outcome, like initial development or prereleases
+    pub notes: Vec<String>,
+}
+
+impl BumpDecision {
+    /// The pull requests that asked for a bump
+    pub fn contributing(&self) -> impl Iterator<Item = &PullDecision> {
+        self.pulls.iter().filter(|pull| pull.level.is_some())
+    }
+
+    /// The pull requests that did not ask for a bump
+    pub fn ignored(&self) -> impl Iterator<Item = &PullDecision> {
+        self.pulls.iter().filter(|pull| pull.level.is_none())
+    }
+
+    /// Render the decision as a plain text table, with one row per pull request
+    pub fn render_table(&self) -> String {
+        let rows: Vec<[String; 4]> = self
+            .pulls
+            .iter()
+            .map(|pull| {
+                let because = match (&pull.ignored_because, pull.contributions.is_empty()) {
+                    (Some(reason), _) => reason.clone(),
+                    (None, true) => String::new(),
+                    (None, false) => pull
+                        .contributions
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                };
+
+                [
+                    format!("#{}", pull.number),
+                    pull.level
+                        .map_or("-".to_string(), |level| level.to_string()),
+                    because,
+                    pull.title.clone(),
+                ]
+            })
+            .collect();
+
+        let header = [
+            "PR".to_string(),
+            "Level".to_string(),
+            "Because".to_string(),
+            "Title".to_string(),
+        ];
+        let mut widths = [0; 4];
+        for row in std::iter::once(&header).chain(&rows) {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let mut table = String::new();
+        for row in std::iter::once(&header).chain(&rows) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            table.push_str(cells.join("  ").trim_end());
+            table.push('\n');
+        }
+
+        let level = match (self.requested_level, self.level) {
+            (Some(requested), Some(level)) if requested != level => {
+                format!("{} (requested {})", level, requested)
+            }
+            (_, Some(level)) => level.to_string(),
+            (_, None) => "none".to_string(),
+        };
+        table.push_str(&format!(
+            "\n{} ➡ {} (bump: {})\n",
+            self.current_version, self.next_version, level
+        ));
+        for note in &self.notes {
+            table.push_str(&format!("{}\n", note));
+        }
+
+        table
+    }
+}
@@ -1,24 +1,36 @@
-use eyre::Result;
+use eyre::{eyre, Context, Result};
 use log::info;
 use regex::Regex;
-use std::{collections::HashSet, fs, path::Path};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    fmt::{self, Display},
+    path::Path,
+};
 
-use semver::Version;
+use semver::{BuildMetadata, Prerelease, Version};
 
-use crate::PullRequest;
+use crate::bump_decision::{BumpDecision, BumpLevel, Contribution, PullDecision};
+use crate::file_change::{FileChange, FileChanges};
+use crate::{FileFormat, PullFilter, PullRequest, VersionTemplate};
 
 type Label = String;
+type CommitType = String;
+#[derive(Default)]
 pub struct BumpRules {
     patch_bump_labels: HashSet<Label>,
     minor_bump_labels: HashSet<Label>,
     major_bump_labels: HashSet<Label>,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
-enum BumpLevel {
-    Patch,
-    Minor,
-    Major,
+    /// The pattern of Conventional Commit titles, when they are a bump source
+    conventional_commits: Option<Regex>,
+    patch_commit_types: HashSet<CommitType>,
+    minor_commit_types: HashSet<CommitType>,
+    major_commit_types: HashSet<CommitType>,
+    prerelease: Option<String>,
+    initial_development: bool,
+    graduation_labels: HashSet<Label>,
+    pull_filter: PullFilter,
 }
 
 impl BumpRules {
@@ -44,6 +56,73 @@ impl BumpRules {
         }
     }
 
+    /// Also find bump levels from Conventional Commit pull request titles
+    ///
+    /// Breaking changes (`feat!: ...` or a `BREAKING CHANGE:` footer) are always a major bump,
+    /// other commit types use the levels given to them with the `add_*_commit_types` methods.
+    pub fn enable_conventional_commits(&mut self) {
+        self.conventional_commits =
+            Some(Regex::new(r"^(?P<type>[a-zA-Z]+)(\([^)]*\))?(?P<breaking>!)?: ").unwrap());
+    }
+
+    pub fn add_patch_commit_types(&mut self, types: Vec<String>) {
+        for commit_type in types {
+            self.patch_commit_types.insert(commit_type);
+        }
+    }
+
+    pub fn add_minor_commit_types(&mut self, types: Vec<String>) {
+        for commit_type in types {
+            self.minor_commit_types.insert(commit_type);
+        }
+    }
+
+    pub fn add_major_commit_types(&mut self, types: Vec<String>) {
+        for commit_type in types {
+            self.major_commit_types.insert(commit_type);
+        }
+    }
+
+    /// Produce prerelease versions like `1.2.3-rc.1` instead of regular releases
+    ///
+    /// The identifier must be a valid semver prerelease identifier (like `alpha`, `beta` or `rc`).
+    pub fn set_prerelease(&mut self, identifier: &str) -> Result<()> {
+        let valid = !identifier.contains('.') && Prerelease::new(identifier).is_ok();
+        if !valid || identifier.is_empty() {
+            return Err(eyre!("Invalid prerelease identifier '{}'", identifier));
+        }
+
+        self.prerelease = Some(identifier.to_string());
+        Ok(())
+    }
+
+    /// Follow Cargo's semantics for `0.y.z` versions
+    ///
+    /// While the major version is 0, a major bump becomes a minor bump and a minor bump becomes a
+    /// patch bump. Pull requests with one of the graduation labels bump the version to `1.0.0`.
+    pub fn enable_initial_development(&mut self) {
+        self.initial_development = true;
+    }
+
+    pub fn add_graduation_labels(&mut self, labels: Vec<String>) {
+        for label in labels {
+            self.graduation_labels.insert(label);
+        }
+    }
+
+    /// Ignore pull requests with the filter, so they do not bump the version
+    pub fn set_pull_filter(&mut self, filter: PullFilter) {
+        self.pull_filter = filter;
+    }
+
+    pub fn pull_filter(&self) -> &PullFilter {
+        &self.pull_filter
+    }
+
+    pub fn prerelease(&self) -> Option<&str> {
+        self.prerelease.as_deref()
+    }
+
     fn label_into_level(&self, label: Label) -> Option<BumpLevel> {
         if self.patch_bump_labels.contains(&label) {
             Some(BumpLevel::Patch)
@@ -55,66 +134,830 @@ impl BumpRules {
             None
         }
     }
-}
 
-impl Default for BumpRules {
-    fn default() -> Self {
-        BumpRules {
-            patch_bump_labels: HashSet::new(),
-            minor_bump_labels: HashSet::new(),
-            major_bump_labels: HashSet::new(),
+    fn title_into_contribution(&self, title: &str, body: Option<&str>) -> Option<Contribution> {
+        let captures = self.conventional_commits.as_ref()?.captures(title.trim())?;
+        let commit_type = captures["type"].to_lowercase();
+
+        let breaking_footer = body.unwrap_or_default().lines().any(|line| {
+            line.starts_with("BREAKING CHANGE:") || line.starts_with("BREAKING-CHANGE:")
+        });
+        if captures.name("breaking").is_some() || breaking_footer {
+            return Some(Contribution::Title {
+                commit_type,
+                breaking: true,
+                level: BumpLevel::Major,
+            });
+        }
+
+        let level = if self.patch_commit_types.contains(&commit_type) {
+            BumpLevel::Patch
+        } else if self.minor_commit_types.contains(&commit_type) {
+            BumpLevel::Minor
+        } else if self.major_commit_types.contains(&commit_type) {
+            BumpLevel::Major
+        } else {
+            return None;
+        };
+
+        Some(Contribution::Title {
+            commit_type,
+            breaking: false,
+            level,
+        })
+    }
+
+    /// Find what a pull request contributes to the next version
+    fn decide_pull(&self, pr: PullRequest) -> PullDecision {
+        if let Some(reason) = self.pull_filter.ignore_reason(&pr) {
+            info!("🗑️  #{} - {} (ignored: {})", pr.number, pr.title, reason);
+            return PullDecision {
+                number: pr.number,
+                title: pr.title,
+                url: pr.url,
+                labels: pr.labels,
+                contributions: Vec::new(),
+                level: None,
+                ignored_because: Some(reason),
+            };
+        }
+
+        let mut contributions: Vec<Contribution> = pr
+            .labels
+            .iter()
+            .filter_map(|label| {
+                self.label_into_level(label.clone())
+                    .map(|level| Contribution::Label {
+                        label: label.clone(),
+                        level,
+                    })
+            })
+            .collect();
+        contributions.extend(self.title_into_contribution(&pr.title, pr.body.as_deref()));
+        if self.initial_development {
+            contributions.extend(
+                pr.labels
+                    .iter()
+                    .filter(|label| self.graduation_labels.contains(*label))
+                    .map(|label| Contribution::Graduation {
+                        label: label.clone(),
+                    }),
+            );
+        }
+
+        let level = contributions
+            .iter()
+            .filter_map(|contribution| match contribution {
+                Contribution::Label { level, .. } | Contribution::Title { level, .. } => {
+                    Some(*level)
+                }
+                Contribution::Graduation { .. } => None,
+            })
+            .max();
+
+        let ignored_because = match (level, contributions.is_empty()) {
+            (Some(_), _) | (None, false) => None,
+            (None, true) if pr.labels.is_empty() && self.conventional_commits.is_none() => {
+                Some("no labels".to_string())
+            }
+            (None, true) if self.conventional_commits.is_some() => {
+                Some("no label in a category and no matching title".to_string())
+            }
+            (None, true) => Some("no label in a category".to_string()),
+        };
+
+        PullDecision {
+            number: pr.number,
+            title: pr.title,
+            url: pr.url,
+            labels: pr.labels,
+            contributions,
+            level,
+            ignored_because,
         }
     }
 }
 
-pub fn bump_version(
+/// Find the next version, along with what every pull request contributed to it
+pub fn explain_bump(
     current_version: &Version,
     rules: &BumpRules,
     pulls: impl Iterator<Item = PullRequest>,
+) -> BumpDecision {
+    if rules.conventional_commits.is_some() {
+        info!("Looking at all pull request labels and titles");
+    } else {
+        info!("Looking at all pull request labels");
+    }
+    let pulls: Vec<PullDecision> = pulls.map(|pr| rules.decide_pull(pr)).collect();
+    let requested_level = pulls.iter().filter_map(|pull| pull.level).max();
+    let graduate = pulls.iter().any(|pull| {
+        pull.contributions
+            .iter()
+            .any(|c| matches!(c, Contribution::Graduation { .. }))
+    });
+
+    let mut notes = Vec::new();
+    let mut bump_level = requested_level;
+    if rules.initial_development && current_version.major == 0 {
+        if graduate {
+            info!("Graduating from initial development to 1.0.0");
+            notes.push("Graduating from initial development to 1.0.0".to_string());
+            bump_level = Some(BumpLevel::Major);
+        } else {
+            // While the major version is 0, minor bumps are breaking and patch bumps are not
+            bump_level = bump_level.map(|level| match level {
+                BumpLevel::Major => BumpLevel::Minor,
+                BumpLevel::Minor | BumpLevel::Patch => BumpLevel::Patch,
+            });
+            if bump_level != requested_level {
+                notes.push(format!(
+                    "{} is in initial development, so bumps are one level lower",
+                    current_version
+                ));
+            }
+        }
+    }
+
+    if let Some(level) = &bump_level {
+        info!("Version bump required: {:?}", level);
+    }
+
+    let next_version = next_version(current_version, rules, bump_level, &mut notes);
+    BumpDecision {
+        current_version: current_version.clone(),
+        next_version,
+        requested_level,
+        level: bump_level,
+        pulls,
+        notes,
+    }
+}
+
+/// Apply a bump level to a version, following the prerelease rules
+fn next_version(
+    current_version: &Version,
+    rules: &BumpRules,
+    bump_level: Option<BumpLevel>,
+    notes: &mut Vec<String>,
 ) -> Version {
     let mut next_version = current_version.clone();
 
-    info!("Looking at all pull request labels");
-    let bump_level = pulls
-        .flat_map(|pr| pr.labels.into_iter())
-        .flat_map(|label| rules.label_into_level(label))
-        .max();
+    if current_version.pre.is_empty() {
+        let level = match bump_level {
+            Some(level) => level,
+            None => return next_version,
+        };
+        next_version = bump_release(current_version, &level);
 
-    if let Some(level) = bump_level {
-        info!("Version bump required: {:?}", level);
+        if let Some(identifier) = &rules.prerelease {
+            notes.push(format!("Releasing a '{}' prerelease", identifier));
+            next_version.pre = prerelease(identifier, 1);
+        }
 
-        match level {
-            BumpLevel::Patch => {
-                next_version.patch += 1;
-            }
-            BumpLevel::Minor => {
-                next_version.patch = 0;
-                next_version.minor += 1;
+        return next_version;
+    }
+
+    // The current version is a prerelease of `release`, which may already include the bump
+    let mut release = current_version.clone();
+    release.pre = Prerelease::EMPTY;
+    release.build = BuildMetadata::EMPTY;
+    let target = match &bump_level {
+        Some(level) if !release_includes(&release, level) => bump_release(&release, level),
+        _ => release.clone(),
+    };
+
+    match &rules.prerelease {
+        Some(identifier) => {
+            if bump_level.is_none() {
+                return next_version;
             }
-            BumpLevel::Major => {
-                next_version.patch = 0;
-                next_version.minor = 0;
-                next_version.major += 1;
+
+            let number = match prerelease_number(&current_version.pre, identifier) {
+                Some(number) if target == release => number + 1,
+                _ => 1,
+            };
+            if target == release {
+                notes.push(format!(
+                    "{} already includes the bump, so only the prerelease number changes",
+                    current_version
+                ));
             }
+            next_version = target;
+            next_version.pre = prerelease(identifier, number);
+        }
+        None => {
+            info!("Graduating prerelease {} to {}", current_version, target);
+            notes.push(format!(
+                "Graduating prerelease {} to {}",
+                current_version, target
+            ));
+            next_version = target;
+        }
+    }
+
+    next_version
+}
+
+/// Bump a version to the next release for a given level, dropping any prerelease or build metadata
+fn bump_release(version: &Version, level: &BumpLevel) -> Version {
+    let mut next_version = version.clone();
+    next_version.pre = Prerelease::EMPTY;
+    next_version.build = BuildMetadata::EMPTY;
+
+    match level {
+        BumpLevel::Patch => {
+            next_version.patch += 1;
+        }
+        BumpLevel::Minor => {
+            next_version.patch = 0;
+            next_version.minor += 1;
+        }
+        BumpLevel::Major => {
+            next_version.patch = 0;
+            next_version.minor = 0;
+            next_version.major += 1;
         }
     }
 
     next_version
 }
 
+/// Whether prereleases of `release` already contain a bump of the given level
+///
+/// For example, `2.0.0-rc.1` is already a major bump, so it includes any other bump level, but
+/// `1.2.3-rc.1` is only a patch bump.
+fn release_includes(release: &Version, level: &BumpLevel) -> bool {
+    match level {
+        BumpLevel::Patch => true,
+        BumpLevel::Minor => release.patch == 0,
+        BumpLevel::Major => release.minor == 0 && release.patch == 0,
+    }
+}
+
+fn prerelease(identifier: &str, number: u64) -> Prerelease {
+    Prerelease::new(&format!("{}.{}", identifier, number))
+        .expect("Prerelease identifiers are validated when they are set")
+}
+
+/// Find `N` in a prerelease like `rc.N`, if the prerelease uses the given identifier
+fn prerelease_number(pre: &Prerelease, identifier: &str) -> Option<u64> {
+    let mut parts = pre.as_str().splitn(2, '.');
+    if parts.next()? != identifier {
+        return None;
+    }
+
+    Some(parts.next().and_then(|n| n.parse().ok()).unwrap_or(0))
+}
+
+/// How many times the current version is expected to appear in a bump file
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Occurrences {
+    /// The version appears at least once, and only the first occurrence is bumped
+    #[default]
+    First,
+    /// The version appears exactly once
+    ExactlyOne,
+    /// The version appears at least once, and every occurrence is bumped
+    AtLeastOne,
+    /// Every version after the prefix is the current version, and there is at least one
+    All,
+}
+
+impl Display for Occurrences {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            Occurrences::First | Occurrences::AtLeastOne => "at least one match of",
+            Occurrences::ExactlyOne => "exactly one match of",
+            Occurrences::All => "every version to match",
+        };
+
+        f.write_str(description)
+    }
+}
+
+/// A version found after the prefix in a bump file
+struct Candidate<'a> {
+    version: &'a str,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+/// Find every version written with the template that comes right after the prefix
+fn find_candidates<'a>(
+    contents: &'a str,
+    version_prefix: &str,
+    template: &VersionTemplate,
+) -> Vec<Candidate<'a>> {
+    let re = Regex::new(&format!(
+        "{}(?P<version>{})",
+        regex::escape(version_prefix),
+        template.pattern()
+    ))
+    .unwrap();
+
+    re.captures_iter(contents)
+        .filter_map(|captures| captures.name("version"))
+        // Skip partial versions, like `1.2` at the start of `1.2.3`
+        .filter(|version| {
+            let mut after = contents[version.end()..].chars();
+            match after.next() {
+                Some('.') => !matches!(after.next(), Some(c) if c.is_ascii_digit()),
+                Some(c) => !c.is_ascii_digit(),
+                None => true,
+            }
+        })
+        .map(|version| Candidate {
+            version: version.as_str(),
+            start: version.start(),
+            end: version.end(),
+            line: contents[..version.start()].matches('\n').count() + 1,
+        })
+        .collect()
+}
+
+fn describe_candidates(candidates: &[Candidate]) -> String {
+    let described: Vec<String> = candidates
+        .iter()
+        .take(5)
+        .map(|c| format!("'{}' (line {})", c.version, c.line))
+        .collect();
+
+    match candidates.len() {
+        0 => "no versions were found".to_string(),
+        n if n > described.len() => format!(
+            "found {} and {} more",
+            described.join(", "),
+            n - described.len()
+        ),
+        _ => format!("found {}", described.join(", ")),
+    }
+}
+
+/// Bump the current version after the prefix, returning the change and how many versions changed
+///
+/// It is an error when the occurrences of the current version do not match what is expected, so a
+/// file is never left unchanged silently.
 pub fn bump_in_file(
     current_version: &Version,
     next_version: &Version,
     version_prefix: &str,
+    template: &VersionTemplate,
+    expected: Occurrences,
     file_path: &Path,
-) -> Result<()> {
+    files: &FileChanges,
+) -> Result<(FileChange, usize)> {
     info!("Updating version in '{}'", file_path.to_string_lossy());
-    let contents = fs::read_to_string(file_path)?;
+    let contents = files.read_existing(file_path)?;
+
+    let current = template.render(current_version);
+    let next = template.render(next_version);
+    let candidates = find_candidates(&contents, version_prefix, template);
+    let (matching, others): (Vec<&Candidate>, Vec<&Candidate>) =
+        candidates.iter().partition(|c| c.version == current);
+
+    let valid = match expected {
+        Occurrences::ExactlyOne => matching.len() == 1,
+        Occurrences::First | Occurrences::AtLeastOne => !matching.is_empty(),
+        Occurrences::All => !matching.is_empty() && others.is_empty(),
+    };
+    if !valid {
+        // Without any version after the prefix, the prefix itself is probably wrong
+        let anywhere;
+        let nearby = if candidates.is_empty() {
+            anywhere = find_candidates(&contents, "", template);
+            &anywhere
+        } else {
+            &candidates
+        };
+
+        return Err(eyre!(
+            "Expected {} '{}{}' in '{}', but {} matched ({})",
+            expected,
+            version_prefix,
+            current,
+            file_path.to_string_lossy(),
+            matching.len(),
+            describe_candidates(nearby)
+        ));
+    }
+
+    // Later occurrences are usually something else at the same version, like a dependency
+    let bumped = match expected {
+        Occurrences::First => &matching[..1],
+        _ => &matching[..],
+    };
+
+    let mut replaced = String::with_capacity(contents.len());
+    let mut last = 0;
+    for candidate in bumped {
+        replaced.push_str(&contents[last..candidate.start]);
+        replaced.push_str(&next);
+        last = candidate.end;
+    }
+    replaced.push_str(&contents[last..]);
+
+    info!(
+        "Found {} occurrence(s) of '{}{}' in '{}', bumped {}",
+        matching.len(),
+        version_prefix,
+        current,
+        file_path.to_string_lossy(),
+        bumped.len()
+    );
+
+    let count = bumped.len();
+    Ok((
+        FileChange::new(file_path.to_path_buf(), Some(contents), replaced),
+        count,
+    ))
+}
+
+pub fn bump_field_in_file(
+    current_version: &Version,
+    next_version: &Version,
+    format: FileFormat,
+    key_path: &str,
+    template: &VersionTemplate,
+    file_path: &Path,
+    files: &FileChanges,
+) -> Result<FileChange> {
+    info!(
+        "Updating version at '{}' in '{}'",
+        key_path,
+        file_path.to_string_lossy()
+    );
+    let contents = files.read_existing(file_path)?;
+
+    let replaced = format
+        .replace_version(
+            &contents,
+            key_path,
+            &template.render(current_version),
+            &template.render(next_version),
+        )
+        .wrap_err(format!(
+            "Could not update the version in '{}'",
+            file_path.to_string_lossy()
+        ))?;
+
+    Ok(FileChange::new(
+        file_path.to_path_buf(),
+        Some(contents),
+        replaced,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IgnoreMode;
+
+    fn pull(number: u64, title: &str, body: Option<&str>, labels: &[&str]) -> PullRequest {
+        let labels = labels.iter().map(|label| label.to_string()).collect();
+        PullRequest::new(
+            number,
+            title.to_string(),
+            body.map(str::to_string),
+            labels,
+            None,
+        )
+    }
+
+    fn conventional_rules() -> BumpRules {
+        let mut rules = BumpRules::new();
+        rules.enable_conventional_commits();
+        rules.add_patch_commit_types(vec!["fix".to_string()]);
+        rules.add_minor_commit_types(vec!["feat".to_string()]);
+        rules
+    }
+
+    #[test]
+    fn breaking_title_is_a_major_bump() {
+        let contribution = conventional_rules().title_into_contribution("feat!: drop v1", None);
+
+        assert_eq!(
+            contribution,
+            Some(Contribution::Title {
+                commit_type: "feat".to_string(),
+                breaking: true,
+                level: BumpLevel::Major,
+            })
+        );
+    }
+
+    #[test]
+    fn scoped_title_uses_the_commit_type() {
+        let contribution = conventional_rules().title_into_contribution("fix(parser): typo", None);
+
+        assert_eq!(
+            contribution,
+            Some(Contribution::Title {
+                commit_type: "fix".to_string(),
+                breaking: false,
+                level: BumpLevel::Patch,
+            })
+        );
+    }
+
+    #[test]
+    fn breaking_change_footer_is_a_major_bump() {
+        let body = "Some details\n\nBREAKING CHANGE: the config format changed";
+        let contribution =
+            conventional_rules().title_into_contribution("fix(config): rename keys", Some(body));
+
+        assert_eq!(
+            contribution,
+            Some(Contribution::Title {
+                commit_type: "fix".to_string(),
+                breaking: true,
+                level: BumpLevel::Major,
+            })
+        );
+    }
+
+    #[test]
+    fn titles_are_ignored_without_conventional_commits() {
+        let rules = BumpRules::new();
+
+        assert_eq!(rules.title_into_contribution("feat!: drop v1", None), None);
+        assert_eq!(
+            conventional_rules().title_into_contribution("Update the README", None),
+            None
+        );
+    }
+
+    #[test]
+    fn title_and_label_bumps_take_the_highest_level() {
+        let mut rules = conventional_rules();
+        rules.add_patch_labels(vec!["bug".to_string()]);
+        let pulls = vec![
+            pull(1, "fix: a bug", None, &["bug"]),
+            pull(2, "feat(cli): a command", None, &[]),
+        ];
+
+        let decision = explain_bump(&Version::new(1, 2, 3), &rules, pulls.into_iter());
+
+        assert_eq!(decision.next_version, Version::new(1, 3, 0));
+    }
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    fn channel(identifier: &str) -> BumpRules {
+        let mut rules = BumpRules::new();
+        rules.set_prerelease(identifier).unwrap();
+        rules
+    }
+
+    fn next(current: &str, rules: &BumpRules, level: Option<BumpLevel>) -> Version {
+        next_version(&version(current), rules, level, &mut Vec::new())
+    }
+
+    fn initial_development_rules() -> BumpRules {
+        let mut rules = BumpRules::new();
+        rules.add_patch_labels(vec!["fix".to_string()]);
+        rules.add_minor_labels(vec!["feat".to_string()]);
+        rules.add_major_labels(vec!["breaking".to_string()]);
+        rules.enable_initial_development();
+        rules.add_graduation_labels(vec!["stable".to_string()]);
+        rules
+    }
+
+    fn bump_with_labels(current: &str, rules: &BumpRules, labels: &[&str]) -> Version {
+        let pulls = vec![pull(1, "Title", None, labels)];
+        explain_bump(&version(current), rules, pulls.into_iter()).next_version
+    }
+
+    #[test]
+    fn initial_development_lowers_bumps_by_one_level() {
+        let rules = initial_development_rules();
+
+        assert_eq!(
+            bump_with_labels("0.3.1", &rules, &["breaking"]),
+            version("0.4.0")
+        );
+        assert_eq!(
+            bump_with_labels("0.3.1", &rules, &["feat"]),
+            version("0.3.2")
+        );
+        assert_eq!(
+            bump_with_labels("0.3.1", &rules, &["fix"]),
+            version("0.3.2")
+        );
+    }
+
+    #[test]
+    fn initial_development_ends_at_1_0_0() {
+        let rules = initial_development_rules();
+
+        assert_eq!(
+            bump_with_labels("1.3.1", &rules, &["breaking"]),
+            version("2.0.0")
+        );
+        assert_eq!(
+            bump_with_labels("1.3.1", &rules, &["feat"]),
+            version("1.4.0")
+        );
+    }
+
+    #[test]
+    fn a_graduation_label_bumps_to_1_0_0() {
+        let rules = initial_development_rules();
+
+        assert_eq!(
+            bump_with_labels("0.3.1", &rules, &["stable", "fix"]),
+            version("1.0.0")
+        );
+    }
+
+    #[test]
+    fn a_release_starts_a_channel() {
+        let next = next("1.2.3", &channel("rc"), Some(BumpLevel::Major));
+
+        assert_eq!(next, version("2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn a_bump_already_in_the_prerelease_increments_the_channel() {
+        let next = next("2.0.0-rc.1", &channel("rc"), Some(BumpLevel::Minor));
+
+        assert_eq!(next, version("2.0.0-rc.2"));
+    }
+
+    #[test]
+    fn a_bump_past_the_prerelease_restarts_the_channel() {
+        let next = next("1.2.3-rc.4", &channel("rc"), Some(BumpLevel::Major));
+
+        assert_eq!(next, version("2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn switching_channels_restarts_the_number() {
+        let next = next("2.0.0-beta.3", &channel("rc"), Some(BumpLevel::Patch));
+
+        assert_eq!(next, version("2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn no_bump_keeps_the_prerelease() {
+        let next = next("2.0.0-rc.1", &channel("rc"), None);
 
-    let re = Regex::new(&format!("{}{}", version_prefix, current_version)).unwrap();
-    let replaced = re.replace(&contents, format!("{}{}", version_prefix, next_version));
+        assert_eq!(next, version("2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn a_prerelease_graduates_without_a_channel() {
+        let mut notes = Vec::new();
+        let next = next_version(
+            &version("2.0.0-rc.2"),
+            &BumpRules::new(),
+            Some(BumpLevel::Minor),
+            &mut notes,
+        );
+
+        assert_eq!(next, version("2.0.0"));
+        assert_eq!(notes, vec!["Graduating prerelease 2.0.0-rc.2 to 2.0.0"]);
+    }
+
+    #[test]
+    fn invalid_identifiers_are_refused() {
+        for identifier in ["", "rc.1", "r c"] {
+            assert!(BumpRules::new().set_prerelease(identifier).is_err());
+        }
+    }
 
-    fs::write(file_path, replaced.as_ref())?;
+    const MANIFEST: &str =
+        "[package]\nversion = \"1.0.0\"\n\n[dependencies]\nother = { version = \"1.0.0\" }\n";
 
-    Ok(())
+    /// Bump `MANIFEST` from 1.0.0 to 1.1.0 with the prefix of the release configuration
+    fn bump_manifest(expected: Occurrences) -> Result<(String, usize)> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        std::fs::write(&path, MANIFEST).unwrap();
+
+        let (change, count) = bump_in_file(
+            &version("1.0.0"),
+            &version("1.1.0"),
+            "version = \"",
+            &VersionTemplate::default(),
+            expected,
+            &path,
+            &FileChanges::new(),
+        )?;
+        Ok((change.after, count))
+    }
+
+    #[test]
+    fn only_the_first_occurrence_is_bumped_by_default() {
+        assert_eq!(
+            bump_manifest(Occurrences::default()).unwrap(),
+            (MANIFEST.replacen("1.0.0", "1.1.0", 1), 1)
+        );
+    }
+
+    #[test]
+    fn every_occurrence_is_bumped_when_asked() {
+        assert_eq!(
+            bump_manifest(Occurrences::AtLeastOne).unwrap(),
+            (MANIFEST.replace("1.0.0", "1.1.0"), 2)
+        );
+        assert!(bump_manifest(Occurrences::ExactlyOne).is_err());
+    }
+
+    #[test]
+    fn explain_records_what_every_pull_contributed() {
+        let mut rules = initial_development_rules();
+        rules.enable_conventional_commits();
+        rules.add_minor_commit_types(vec!["feat".to_string()]);
+        rules.set_pull_filter(PullFilter::new(
+            vec!["skip".to_string()],
+            IgnoreMode::Any,
+            true,
+        ));
+        let pulls = vec![
+            pull(1, "feat: add a parser", None, &["fix"]),
+            pull(2, "Stabilize the API", None, &["stable"]),
+            pull(3, "fix: a typo", None, &["skip"]),
+        ];
+
+        let decision = explain_bump(&version("0.3.1"), &rules, pulls.into_iter());
+
+        let numbers: Vec<u64> = decision.pulls.iter().map(|pull| pull.number).collect();
+        assert_eq!(numbers, vec![1, 2, 3]);
+
+        let label_and_title = &decision.pulls[0];
+        assert_eq!(
+            label_and_title.contributions,
+            vec![
+                Contribution::Label {
+                    label: "fix".to_string(),
+                    level: BumpLevel::Patch
+                },
+                Contribution::Title {
+                    commit_type: "feat".to_string(),
+                    breaking: false,
+                    level: BumpLevel::Minor
+                }
+            ]
+        );
+        assert_eq!(label_and_title.level, Some(BumpLevel::Minor));
+        assert_eq!(label_and_title.ignored_because, None);
+
+        let graduation = &decision.pulls[1];
+        assert_eq!(
+            graduation.contributions,
+            vec![Contribution::Graduation {
+                label: "stable".to_string()
+            }]
+        );
+        assert_eq!(graduation.level, None);
+
+        let ignored = &decision.pulls[2];
+        assert!(ignored.contributions.is_empty());
+        assert_eq!(ignored.level, None);
+        assert_eq!(
+            ignored.ignored_because.as_deref(),
+            Some("ignored label 'skip'")
+        );
+
+        assert_eq!(decision.next_version, version("1.0.0"));
+        assert_eq!(
+            decision.notes,
+            vec!["Graduating from initial development to 1.0.0"]
+        );
+    }
+
+    #[test]
+    fn the_table_shows_every_pull_and_the_initial_development_note() {
+        let mut rules = initial_development_rules();
+        rules.set_pull_filter(PullFilter::new(
+            vec!["skip".to_string()],
+            IgnoreMode::Any,
+            true,
+        ));
+        let pulls = vec![
+            pull(1, "Replace the parser", None, &["breaking"]),
+            pull(2, "Update the readme", None, &["skip"]),
+        ];
+
+        let decision = explain_bump(&version("0.3.1"), &rules, pulls.into_iter());
+
+        assert_eq!(decision.requested_level, Some(BumpLevel::Major));
+        assert_eq!(decision.level, Some(BumpLevel::Minor));
+        assert_eq!(
+            decision.render_table(),
+            concat!(
+                "PR  Level  Because                   Title\n",
+                "#1  major  label 'breaking' (major)  Replace the parser\n",
+                "#2  -      ignored label 'skip'      Update the readme\n",
+                "\n",
+                "0.3.1 ➡ 0.4.0 (bump: minor (requested major))\n",
+                "0.3.1 is in initial development, so bumps are one level lower\n",
+            )
+        );
+    }
 }
@@ -0,0 +1,569 @@
+use eyre::{eyre, Context, Result};
+use log::{info, warn};
+use semver::{Comparator, Version, VersionReq};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use toml_edit::{DocumentMut, Item, TableLike, Value};
+
+use crate::file_change::{FileChange, FileChanges};
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A manifest of the workspace, with its edits applied in memory
+struct Manifest {
+    path: PathBuf,
+    contents: String,
+    document: DocumentMut,
+    changed: bool,
+}
+
+impl Manifest {
+    fn read(path: PathBuf, files: &FileChanges) -> Result<Self> {
+        let (contents, document) = read_document(&path, "manifest", files)?;
+
+        Ok(Manifest {
+            path,
+            contents,
+            document,
+            changed: false,
+        })
+    }
+
+    fn into_change(self) -> FileChange {
+        let after = self.document.to_string();
+        FileChange::new(self.path, Some(self.contents), after)
+    }
+
+    fn package_name(&self) -> Option<&str> {
+        self.document.get("package")?.get("name")?.as_str()
+    }
+}
+
+/// Read and parse a TOML file of the workspace, described as `kind` in errors
+fn read_document(path: &Path, kind: &str, files: &FileChanges) -> Result<(String, DocumentMut)> {
+    let contents = files.read_existing(path).wrap_err(format!(
+        "Could not read {} at '{}'",
+        kind,
+        path.to_string_lossy()
+    ))?;
+    let document = contents.parse().wrap_err(format!(
+        "Could not parse {} at '{}'",
+        kind,
+        path.to_string_lossy()
+    ))?;
+
+    Ok((contents, document))
+}
+
+/// Compute the changes to every manifest of a Cargo workspace (and its `Cargo.lock`)
+///
+/// Workspace members are discovered from the root manifest's `workspace.members` (minus
+/// `workspace.exclude`), and the root package is included when there is one. The version of every
+/// crate that is at `current_version` (including `workspace.package.version`) is bumped, along with
+/// the dependency requirements and the `Cargo.lock` entries that point at those crates. Only the
+/// files that change are returned.
+pub fn bump_workspace(
+    current_version: &Version,
+    next_version: &Version,
+    root_manifest: &Path,
+    files: &FileChanges,
+) -> Result<Vec<FileChange>> {
+    if current_version == next_version {
+        return Ok(Vec::new());
+    }
+
+    let current = current_version.to_string();
+    let next = next_version.to_string();
+
+    let mut root = Manifest::read(root_manifest.to_path_buf(), files)?;
+    let mut members = Vec::new();
+    for path in member_manifests(&root)? {
+        members.push(Manifest::read(path, files)?);
+    }
+
+    // Bump the versions of the crates
+    let mut workspace_version_bumped = false;
+    if let Some(version) = root
+        .document
+        .get_mut("workspace")
+        .and_then(|w| w.get_mut("package"))
+        .and_then(|p| p.get_mut("version"))
+    {
+        if set_string_if(version, &current, &next) {
+            info!("Bumped 'workspace.package.version'");
+            workspace_version_bumped = true;
+            root.changed = true;
+        } else {
+            // Another bump target may already have bumped it
+            workspace_version_bumped = version.as_str() == Some(next.as_str());
+        }
+    }
+
+    let mut bumped = HashSet::new();
+    for manifest in std::iter::once(&mut root).chain(members.iter_mut()) {
+        let name = match manifest.package_name() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let version = match manifest
+            .document
+            .get_mut("package")
+            .and_then(|p| p.get_mut("version"))
+        {
+            Some(version) => version,
+            None => continue,
+        };
+
+        let inherited = version
+            .get("workspace")
+            .and_then(|w| w.as_bool())
+            .unwrap_or(false);
+        if inherited {
+            if workspace_version_bumped {
+                bumped.insert(name);
+            }
+        } else if set_string_if(version, &current, &next) {
+            info!("Bumped '{}' in '{}'", name, manifest.path.to_string_lossy());
+            manifest.changed = true;
+            bumped.insert(name);
+        } else if version.as_str() == Some(next.as_str()) {
+            bumped.insert(name);
+        }
+    }
+
+    if bumped.is_empty() {
+        warn!("No crate in the workspace is at version {}", current);
+    }
+
+    // Bump the requirements on the bumped crates
+    for manifest in std::iter::once(&mut root).chain(members.iter_mut()) {
+        let path = manifest.path.to_string_lossy().to_string();
+        let mut changed = false;
+
+        let document = manifest.document.as_table_mut();
+        for table in DEPENDENCY_TABLES.iter() {
+            if let Some(dependencies) = document.get_mut(table).and_then(Item::as_table_like_mut) {
+                changed |=
+                    bump_requirements(dependencies, &bumped, current_version, next_version, &path);
+            }
+        }
+
+        if let Some(targets) = document.get_mut("target").and_then(Item::as_table_like_mut) {
+            for (_, target) in targets.iter_mut() {
+                for table in DEPENDENCY_TABLES.iter() {
+                    if let Some(dependencies) =
+                        target.get_mut(table).and_then(Item::as_table_like_mut)
+                    {
+                        changed |= bump_requirements(
+                            dependencies,
+                            &bumped,
+                            current_version,
+                            next_version,
+                            &path,
+                        );
+                    }
+                }
+            }
+        }
+
+        if let Some(dependencies) = document
+            .get_mut("workspace")
+            .and_then(|w| w.get_mut("dependencies"))
+            .and_then(Item::as_table_like_mut)
+        {
+            changed |=
+                bump_requirements(dependencies, &bumped, current_version, next_version, &path);
+        }
+
+        manifest.changed |= changed;
+    }
+
+    let mut changes: Vec<FileChange> = std::iter::once(root)
+        .chain(members)
+        .filter(|manifest| manifest.changed)
+        .map(Manifest::into_change)
+        .collect();
+
+    let lock_path = root_manifest.with_file_name("Cargo.lock");
+    if lock_path.exists() && !bumped.is_empty() {
+        if let Some(lock) = bump_lock_file(&lock_path, &bumped, &current, &next, files)? {
+            changes.push(lock);
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Find the manifests of the workspace members, excluding the root manifest
+fn member_manifests(root: &Manifest) -> Result<Vec<PathBuf>> {
+    let root_dir = root.path.parent().unwrap_or_else(|| Path::new("."));
+    let workspace = match root.document.get("workspace") {
+        Some(workspace) => workspace,
+        None => return Ok(Vec::new()),
+    };
+
+    let patterns = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(Item::as_array)
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = patterns("exclude")
+        .iter()
+        .map(|e| root_dir.join(e))
+        .collect();
+
+    let mut manifests = Vec::new();
+    for member in patterns("members") {
+        let pattern = root_dir.join(&member);
+        let directories = glob::glob(&pattern.to_string_lossy())
+            .wrap_err(format!("Invalid workspace member pattern '{}'", member))?;
+
+        for directory in directories {
+            let directory = directory?;
+            let manifest = directory.join("Cargo.toml");
+            if excluded.iter().any(|e| directory.starts_with(e)) || !manifest.is_file() {
+                continue;
+            }
+            if manifest != root.path && !manifests.contains(&manifest) {
+                manifests.push(manifest);
+            }
+        }
+    }
+
+    Ok(manifests)
+}
+
+/// Bump the version requirements of the dependencies on bumped crates
+fn bump_requirements(
+    dependencies: &mut dyn TableLike,
+    bumped: &HashSet<String>,
+    current_version: &Version,
+    next_version: &Version,
+    manifest: &str,
+) -> bool {
+    let mut changed = false;
+
+    for (key, dependency) in dependencies.iter_mut() {
+        // Renamed dependencies point at another package
+        let name = dependency
+            .get("package")
+            .and_then(Item::as_str)
+            .unwrap_or_else(|| key.get())
+            .to_string();
+        if !bumped.contains(&name) {
+            continue;
+        }
+
+        let requirement = match dependency {
+            Item::Value(Value::String(_)) => dependency,
+            _ => match dependency.get_mut("version") {
+                Some(requirement) => requirement,
+                None => continue, // Path-only dependency
+            },
+        };
+
+        let previous = match requirement.as_str() {
+            Some(previous) => previous.to_string(),
+            None => continue,
+        };
+        match bump_requirement(&previous, current_version, next_version) {
+            Some(updated) => {
+                set_string(requirement, &updated);
+                info!(
+                    "Bumped the requirement on '{}' in '{}' ({} ➡ {})",
+                    name, manifest, previous, updated
+                );
+                changed = true;
+            }
+            None if !requirement_matches(&previous, next_version) => warn!(
+                "The requirement '{}' on '{}' in '{}' does not match the new version {}",
+                previous, name, manifest, next_version
+            ),
+            None => {}
+        }
+    }
+
+    changed
+}
+
+/// Rewrite the comparators of a requirement that name exactly the current version, like `1.2.3`
+/// or `=1.2.3` in `>=1.2.3, <2`, returning `None` when there are none
+///
+/// The rest of the requirement is kept as written, and partial versions like `1.2` are left alone.
+fn bump_requirement(
+    requirement: &str,
+    current_version: &Version,
+    next_version: &Version,
+) -> Option<String> {
+    VersionReq::parse(requirement).ok()?;
+
+    let current = current_version.to_string();
+    let next = next_version.to_string();
+    let mut changed = false;
+    let comparators: Vec<String> = requirement
+        .split(',')
+        .map(|comparator| {
+            let names_current = Comparator::parse(comparator.trim()).is_ok_and(|c| {
+                c.major == current_version.major
+                    && c.minor == Some(current_version.minor)
+                    && c.patch == Some(current_version.patch)
+                    && c.pre == current_version.pre
+            });
+            if names_current {
+                changed = true;
+                comparator.replacen(&current, &next, 1)
+            } else {
+                comparator.to_string()
+            }
+        })
+        .collect();
+
+    changed.then(|| comparators.join(","))
+}
+
+fn requirement_matches(requirement: &str, version: &Version) -> bool {
+    match VersionReq::parse(requirement) {
+        Ok(requirement) => requirement.matches(version),
+        _ => false,
+    }
+}
+
+/// Bump the workspace crates in `Cargo.lock`, returning the change if anything changed
+///
+/// Workspace crates are the lock entries without a `source`.
+fn bump_lock_file(
+    lock_path: &Path,
+    bumped: &HashSet<String>,
+    current: &str,
+    next: &str,
+    files: &FileChanges,
+) -> Result<Option<FileChange>> {
+    let (contents, mut document) = read_document(lock_path, "lockfile", files)?;
+    let mut changed = false;
+
+    let packages = document
+        .get_mut("package")
+        .and_then(Item::as_array_of_tables_mut)
+        .ok_or_else(|| eyre!("No packages found in '{}'", lock_path.to_string_lossy()))?;
+
+    for package in packages.iter_mut() {
+        let is_bumped = package.get("source").is_none()
+            && matches!(package.get("name").and_then(Item::as_str), Some(name) if bumped.contains(name));
+        if is_bumped {
+            if let Some(version) = package.get_mut("version") {
+                changed |= set_string_if(version, current, next);
+            }
+        }
+
+        // Dependencies include the version when several versions of a crate are locked
+        if let Some(dependencies) = package.get_mut("dependencies").and_then(Item::as_array_mut) {
+            for dependency in dependencies.iter_mut() {
+                let updated = match dependency.as_str() {
+                    Some(entry) => bumped
+                        .iter()
+                        .find(|name| entry == format!("{} {}", name, current))
+                        .map(|name| format!("{} {}", name, next)),
+                    None => None,
+                };
+
+                if let Some(updated) = updated {
+                    let decor = dependency.decor().clone();
+                    *dependency = Value::from(updated);
+                    *dependency.decor_mut() = decor;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    if changed {
+        info!(
+            "Bumped workspace crates in '{}'",
+            lock_path.to_string_lossy()
+        );
+        Ok(Some(FileChange::new(
+            lock_path.to_path_buf(),
+            Some(contents),
+            document.to_string(),
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Set a string value if it currently equals `expected`, returning whether it changed
+fn set_string_if(item: &mut Item, expected: &str, value: &str) -> bool {
+    if item.as_str() != Some(expected) {
+        return false;
+    }
+
+    set_string(item, value);
+    true
+}
+
+/// Set a string value, keeping the whitespace and comments around it
+fn set_string(item: &mut Item, value: &str) {
+    if let Some(current) = item.as_value_mut() {
+        let decor = current.decor().clone();
+        *current = Value::from(value);
+        *current.decor_mut() = decor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn version(version: &str) -> Version {
+        Version::parse(version).unwrap()
+    }
+
+    /// Bump a workspace of `files` from 1.0.0 to 1.1.0, returning the new contents by path
+    fn bump(files: &[(&str, &str)]) -> Vec<(String, String)> {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in files {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+
+        bump_workspace(
+            &version("1.0.0"),
+            &version("1.1.0"),
+            &dir.path().join("Cargo.toml"),
+            &FileChanges::new(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|change| {
+            let path = change.path.strip_prefix(dir.path()).unwrap();
+            (path.to_string_lossy().into_owned(), change.after)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn requirements_on_bumped_crates_are_rewritten() {
+        let changes = bump(&[
+            ("Cargo.toml", "[workspace]\nmembers = [\"a\", \"b\"]\n"),
+            (
+                "a/Cargo.toml",
+                "[package]\nname = \"a\"\nversion = \"1.0.0\"\n",
+            ),
+            (
+                "b/Cargo.toml",
+                concat!(
+                    "[package]\nname = \"b\"\nversion = \"2.0.0\"\n\n",
+                    "[dependencies]\n",
+                    "a = { path = \"../a\", version = \"=1.0.0\" } # pinned\n",
+                    "renamed = { package = \"a\", version = \">=1.0.0, <2\" }\n",
+                    "other = \"1.0.0\"\n\n",
+                    "[dev-dependencies]\n",
+                    "a = { path = \"../a\" }\n\n",
+                    "[build-dependencies.a]\nversion = \"1.0\"\n",
+                ),
+            ),
+        ]);
+
+        assert_eq!(
+            changes,
+            vec![
+                (
+                    "a/Cargo.toml".to_string(),
+                    "[package]\nname = \"a\"\nversion = \"1.1.0\"\n".to_string()
+                ),
+                (
+                    "b/Cargo.toml".to_string(),
+                    concat!(
+                        "[package]\nname = \"b\"\nversion = \"2.0.0\"\n\n",
+                        "[dependencies]\n",
+                        "a = { path = \"../a\", version = \"=1.1.0\" } # pinned\n",
+                        "renamed = { package = \"a\", version = \">=1.1.0, <2\" }\n",
+                        "other = \"1.0.0\"\n\n",
+                        "[dev-dependencies]\n",
+                        "a = { path = \"../a\" }\n\n",
+                        "[build-dependencies.a]\nversion = \"1.0\"\n",
+                    )
+                    .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_comparators_on_the_current_version_are_rewritten() {
+        let (current, next) = (version("1.0.0"), version("1.1.0"));
+
+        assert_eq!(
+            bump_requirement("^1.0.0", &current, &next),
+            Some("^1.1.0".to_string())
+        );
+        assert_eq!(
+            bump_requirement(">=0.9.0, <=1.0.0", &current, &next),
+            Some(">=0.9.0, <=1.1.0".to_string())
+        );
+        assert_eq!(bump_requirement("1.0", &current, &next), None);
+        assert_eq!(bump_requirement("11.0.0", &current, &next), None);
+        assert_eq!(bump_requirement("1.0.0-beta.1", &current, &next), None);
+        assert_eq!(bump_requirement("not a requirement", &current, &next), None);
+    }
+
+    #[test]
+    fn lockfile_entries_of_bumped_crates_are_rewritten() {
+        let lock = concat!(
+            "version = 3\n\n",
+            "[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n",
+            "dependencies = [\n \"a 1.0.0\",\n \"serde\",\n]\n\n",
+            "[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\n",
+            "source = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        );
+        let changes = bump(&[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"a\"\nversion = \"1.0.0\"\n",
+            ),
+            ("Cargo.lock", lock),
+        ]);
+
+        assert_eq!(
+            changes[1],
+            (
+                "Cargo.lock".to_string(),
+                lock.replacen("version = \"1.0.0\"", "version = \"1.1.0\"", 1)
+                    .replace("\"a 1.0.0\"", "\"a 1.1.0\"")
+            )
+        );
+    }
+
+    #[test]
+    fn an_invalid_lockfile_is_reported_as_a_lockfile() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("Cargo.lock"), "[[package]\n").unwrap();
+
+        let error = bump_workspace(
+            &version("1.0.0"),
+            &version("1.1.0"),
+            &dir.path().join("Cargo.toml"),
+            &FileChanges::new(),
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().starts_with("Could not parse lockfile at"));
+    }
+}
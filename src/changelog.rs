@@ -0,0 +1,140 @@
+use chrono::{DateTime, Utc};
+use eyre::{Context, Result};
+use log::info;
+use std::path::Path;
+
+use crate::{
+    file_change::{FileChange, FileChanges},
+    PullRequest,
+};
+
+type Label = String;
+struct Section {
+    title: String,
+    labels: Vec<Label>,
+}
+
+/// Markdown changelog where pull requests are grouped in titled sections
+///
+/// A pull request goes in the first section that has one of its labels. Pull requests that do not
+/// fit in any section are listed under "Other Changes".
+#[derive(Default)]
+pub struct Changelog {
+    sections: Vec<Section>,
+}
+
+impl Changelog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_section(&mut self, title: String, labels: Vec<Label>) {
+        self.sections.push(Section { title, labels });
+    }
+
+    pub fn render(&self, pulls: &[PullRequest]) -> String {
+        let mut sections: Vec<(&str, Vec<&PullRequest>)> = self
+            .sections
+            .iter()
+            .map(|section| (section.title.as_str(), Vec::new()))
+            .collect();
+        let mut others = Vec::new();
+
+        for pr in pulls {
+            let section = self
+                .sections
+                .iter()
+                .position(|section| pr.labels.iter().any(|l| section.labels.contains(l)));
+
+            match section {
+                Some(index) => sections[index].1.push(pr),
+                None => others.push(pr),
+            }
+        }
+        sections.push(("## Other Changes", others));
+
+        sections
+            .into_iter()
+            .filter(|(_, pulls)| !pulls.is_empty())
+            .map(|(title, pulls)| {
+                let lines: Vec<String> = pulls.into_iter().map(render_pull).collect();
+                format!("{}\n\n{}\n", title, lines.join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn render_pull(pr: &PullRequest) -> String {
+    let number = match &pr.url {
+        Some(url) => format!("[#{}]({})", pr.number, url),
+        None => format!("#{}", pr.number),
+    };
+
+    match &pr.author {
+        Some(author) => format!("- {} ({}) by @{}", pr.title, number, author),
+        None => format!("- {} ({})", pr.title, number),
+    }
+}
+
+/// Add a changelog entry for a version at the top of a changelog file
+///
+/// The entry's heading is the version's tag name, like `v1.2.3`, and the file is created if it
+/// does not exist yet.
+pub fn prepend_to_file(
+    tag_name: &str,
+    date: &DateTime<Utc>,
+    changelog: &str,
+    file_path: &Path,
+    files: &FileChanges,
+) -> Result<FileChange> {
+    info!(
+        "Adding changelog entry to '{}'",
+        file_path.to_string_lossy()
+    );
+    let existing = files.read(file_path).wrap_err(format!(
+        "Could not read changelog at '{}'",
+        file_path.to_string_lossy()
+    ))?;
+
+    let entry = format!(
+        "# {} ({})\n\n{}",
+        tag_name,
+        date.format("%Y-%m-%d"),
+        changelog
+    );
+    let contents = match existing.as_deref() {
+        None | Some("") => entry,
+        Some(existing) => format!("{}\n{}", entry, existing),
+    };
+
+    Ok(FileChange::new(file_path.to_path_buf(), existing, contents))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn the_entry_heading_is_the_tag_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("CHANGELOG.md");
+        std::fs::write(&path, "# release-1.0.0 (2021-01-01)\n").unwrap();
+        let date = Utc.ymd(2021, 2, 3).and_hms(0, 0, 0);
+
+        let change = prepend_to_file(
+            "release-1.1.0",
+            &date,
+            "- A change\n",
+            &path,
+            &FileChanges::new(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            change.after,
+            "# release-1.1.0 (2021-02-03)\n\n- A change\n\n# release-1.0.0 (2021-01-01)\n"
+        );
+    }
+}
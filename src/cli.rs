@@ -0,0 +1,189 @@
+use eyre::{eyre, Result};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: pr_bump_bin [COMMAND] [OPTIONS]
+
+Without a command, runs as a GitHub Action configured by its environment.
+
+Commands:
+  next-version     Print the next version
+  apply            Update the files with the next version
+  explain          Show the pull requests that decide the next version
+  preview          Comment the next version on the pull request in GITHUB_EVENT_PATH
+  check            Check that the pull request in GITHUB_EVENT_PATH has a category label
+  validate-config  Check the configuration file given with --config
+  schema           Print the JSON Schema of the configuration
+
+Options:
+  --repo <OWNER/NAME>    The GitHub repository [env: GITHUB_REPOSITORY]
+  --workspace <PATH>     The repository's root [env: GITHUB_WORKSPACE, default: .]
+  --config <PATH>        The configuration file, relative to the workspace [env: INPUT_CONFIGURATION]
+  --token <TOKEN>        The GitHub token [env: GITHUB_TOKEN]
+  --backend <BACKEND>    Where to read releases from, 'github' or 'git' [env: INPUT_BACKEND]
+  --dry-run              Show the changes instead of writing them [env: INPUT_DRY_RUN]
+  -h, --help             Print this help";
+
+/// What to do when the binary runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Run as a GitHub Action: update files, publish the release and set outputs
+    Action,
+    NextVersion,
+    Apply,
+    Explain,
+    /// Comment the next version on a pull request, as if it was merged
+    Preview,
+    /// Check that a pull request has a label that decides the version bump
+    Check,
+    ValidateConfig,
+    Schema,
+    Help,
+}
+
+/// The command line, where every option falls back to the environment when it is not given
+#[derive(Debug)]
+pub struct CliArgs {
+    pub command: Command,
+    pub repo: Option<String>,
+    pub workspace: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub token: Option<String>,
+    pub backend: Option<String>,
+    pub dry_run: bool,
+}
+
+impl CliArgs {
+    /// Parse the arguments, without the binary's name
+    ///
+    /// Options take their value as the next argument or after `=` (like `--repo=owner/name`).
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut cli_args = CliArgs {
+            command: Command::Action,
+            repo: None,
+            workspace: None,
+            config: None,
+            token: None,
+            backend: None,
+            dry_run: false,
+        };
+
+        let mut args = args.into_iter();
+        let mut command = None;
+        while let Some(arg) = args.next() {
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| eyre!("Missing value for '{}'", option))
+            };
+
+            match option.as_str() {
+                "--repo" => cli_args.repo = Some(value()?),
+                "--workspace" => cli_args.workspace = Some(PathBuf::from(value()?)),
+                "--config" => cli_args.config = Some(PathBuf::from(value()?)),
+                "--token" => cli_args.token = Some(value()?),
+                "--backend" => cli_args.backend = Some(value()?),
+                "--dry-run" => cli_args.dry_run = true,
+                "-h" | "--help" => command = Some(Command::Help),
+                other if other.starts_with('-') => {
+                    return Err(eyre!(
+                        "Unknown option '{}', run with --help for usage",
+                        other
+                    ))
+                }
+                other if command.is_none() => command = Some(Command::parse(other)?),
+                other => {
+                    return Err(eyre!(
+                        "Unexpected argument '{}', run with --help for usage",
+                        other
+                    ))
+                }
+            }
+        }
+
+        if let Some(command) = command {
+            cli_args.command = command;
+        }
+        Ok(cli_args)
+    }
+}
+
+impl Command {
+    fn parse(command: &str) -> Result<Self> {
+        match command {
+            "next-version" => Ok(Command::NextVersion),
+            "apply" => Ok(Command::Apply),
+            "explain" => Ok(Command::Explain),
+            "preview" => Ok(Command::Preview),
+            "check" => Ok(Command::Check),
+            "validate-config" => Ok(Command::ValidateConfig),
+            "schema" => Ok(Command::Schema),
+            "help" => Ok(Command::Help),
+            other => Err(eyre!(
+                "Unknown command '{}', run with --help for usage",
+                other
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs> {
+        CliArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_take_a_value_after_a_space_or_an_equals_sign() {
+        let cli_args = parse(&[
+            "apply",
+            "--repo=owner/name",
+            "--config",
+            "pr-bump.yaml",
+            "--token=a=b",
+            "--dry-run",
+        ])
+        .unwrap();
+
+        assert_eq!(cli_args.command, Command::Apply);
+        assert_eq!(cli_args.repo.as_deref(), Some("owner/name"));
+        assert_eq!(cli_args.config, Some(PathBuf::from("pr-bump.yaml")));
+        assert_eq!(cli_args.token.as_deref(), Some("a=b"));
+        assert!(cli_args.dry_run);
+    }
+
+    #[test]
+    fn without_a_command_the_action_runs() {
+        assert_eq!(parse(&[]).unwrap().command, Command::Action);
+    }
+
+    #[test]
+    fn invalid_command_lines_are_refused() {
+        for (args, error) in [
+            (vec!["next-version", "--repo"], "Missing value for '--repo'"),
+            (
+                vec!["--verbose"],
+                "Unknown option '--verbose', run with --help for usage",
+            ),
+            (
+                vec!["bump"],
+                "Unknown command 'bump', run with --help for usage",
+            ),
+            (
+                vec!["apply", "explain"],
+                "Unexpected argument 'explain', run with --help for usage",
+            ),
+        ] {
+            assert_eq!(parse(&args).unwrap_err().to_string(), error);
+        }
+    }
+}
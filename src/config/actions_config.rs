@@ -2,11 +2,83 @@ use eyre::{eyre, Context, Result};
 use log::info;
 use std::{env, path::PathBuf};
 
+use crate::cli::CliArgs;
+
+/// Whether the action should fail when an error occurs, from `INPUT_FAIL_ON_ERROR`
+///
+/// This is read on its own so that it is available even when the rest of the configuration is
+/// invalid. It defaults to `true`.
+pub fn fail_on_error_from_env() -> bool {
+    !matches!(
+        env::var("INPUT_FAIL_ON_ERROR").as_deref(),
+        Ok("false") | Ok("False") | Ok("FALSE")
+    )
+}
+
 pub struct ActionConfig {
-    pub repo: Repo,
+    pub mode: Mode,
+    pub backend: Backend,
+    pub repo: Option<Repo>,
     pub workspace: PathBuf,
+
+    /// The configuration file's path, already joined with the workspace
     pub configuration_file: Option<PathBuf>,
     pub github_token: Option<String>,
+    pub sha: Option<String>,
+    pub branch: Option<String>,
+    pub dry_run: bool,
+}
+
+/// What the action does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Bump the version, update files and publish the release
+    Release,
+    /// Comment the next version on the pull request that triggered the run
+    Preview,
+    /// Check that the pull request that triggered the run has a label that decides the bump
+    Check,
+}
+
+impl Mode {
+    pub fn try_from_env() -> Result<Self> {
+        info!("Reading value for INPUT_MODE");
+        match env::var("INPUT_MODE").unwrap_or_default().as_str() {
+            "" | "release" => Ok(Mode::Release),
+            "preview" => Ok(Mode::Preview),
+            "check" => Ok(Mode::Check),
+            other => Err(eyre!(
+                "Unknown mode '{}', expected 'release', 'preview' or 'check'",
+                other
+            )),
+        }
+    }
+}
+
+/// Where releases and pull requests are read from
+pub enum Backend {
+    /// The GitHub API
+    GitHub,
+    /// The git history of the checked-out repository, without any network access
+    Git,
+}
+
+impl Backend {
+    pub fn try_from_env() -> Result<Self> {
+        info!("Reading value for INPUT_BACKEND");
+        Self::parse(&env::var("INPUT_BACKEND").unwrap_or_default())
+    }
+
+    fn parse(backend: &str) -> Result<Self> {
+        match backend {
+            "" | "github" => Ok(Backend::GitHub),
+            "git" => Ok(Backend::Git),
+            other => Err(eyre!(
+                "Unknown backend '{}', expected 'github' or 'git'",
+                other
+            )),
+        }
+    }
 }
 
 pub struct Repo {
@@ -20,7 +92,12 @@ impl Repo {
         let repo_info = env::var("GITHUB_REPOSITORY")
             .wrap_err("Could not read GITHUB_REPOSITORY to gather repository info")?;
 
-        let mut parts = repo_info.split('/');
+        Self::parse(&repo_info)
+    }
+
+    /// Parse a repository written as `owner/name`
+    pub fn parse(repo_info: &str) -> Result<Self> {
+        let mut parts = repo_info.split('/').filter(|part| !part.is_empty());
         Ok(Repo {
             owner: parts
                 .next()
@@ -35,14 +112,81 @@ impl Repo {
 }
 
 impl ActionConfig {
+    /// Read the configuration of the action, when it runs in GitHub Actions
     pub fn try_from_env() -> Result<Self> {
         info!("Reading value for GITHUB_WORKSPACE");
         let workspace_path = env::var("GITHUB_WORKSPACE")
             .wrap_err("Could not read GITHUB_WORKSPACE to find the workspace path")?;
-        let workspace_path = PathBuf::from(workspace_path);
 
+        Ok(ActionConfig {
+            mode: Mode::try_from_env()?,
+            repo: Some(Repo::try_from_env()?),
+            ..Self::from_parts(
+                PathBuf::from(workspace_path),
+                None,
+                None,
+                Backend::try_from_env()?,
+                false,
+            )
+        })
+    }
+
+    /// Read the configuration from the command line, falling back to the environment
+    ///
+    /// Unlike in GitHub Actions, the workspace defaults to the current directory, and the
+    /// repository is optional since only the `github` backend needs it.
+    pub fn try_from_cli(args: &CliArgs) -> Result<Self> {
+        let workspace = match &args.workspace {
+            Some(workspace) => workspace.clone(),
+            None => env::var("GITHUB_WORKSPACE")
+                .map(PathBuf::from)
+                .or_else(|_| env::current_dir())
+                .wrap_err("Could not find the workspace path")?,
+        };
+
+        let backend = match &args.backend {
+            Some(backend) => Backend::parse(backend)?,
+            None => Backend::try_from_env()?,
+        };
+
+        let repo = match &args.repo {
+            Some(repo) => Some(Repo::parse(repo)?),
+            None => Repo::try_from_env().ok(),
+        };
+
+        let mut action_config = ActionConfig {
+            repo,
+            ..Self::from_parts(
+                workspace,
+                args.config
+                    .as_ref()
+                    .map(|config| config.to_string_lossy().into_owned()),
+                args.token.clone(),
+                backend,
+                args.dry_run,
+            )
+        };
+
+        // Locally, pull requests are read up to the checked-out commit
+        if let (Backend::Git, None) = (&action_config.backend, &action_config.sha) {
+            action_config.sha = Some("HEAD".to_string());
+        }
+
+        Ok(action_config)
+    }
+
+    /// Read the settings shared by the action and the command line, where the given values take
+    /// precedence over the environment
+    fn from_parts(
+        workspace_path: PathBuf,
+        configuration_file: Option<String>,
+        github_token: Option<String>,
+        backend: Backend,
+        dry_run: bool,
+    ) -> Self {
         info!("Reading value for INPUT_CONFIGURATION");
-        let configuration_file = env::var("INPUT_CONFIGURATION").ok();
+        let configuration_file =
+            configuration_file.or_else(|| env::var("INPUT_CONFIGURATION").ok());
         let configuration_file = match configuration_file {
             Some(value) => {
                 if value.is_empty() {
@@ -55,13 +199,55 @@ impl ActionConfig {
         };
 
         info!("Reading value for GITHUB_TOKEN");
-        let github_token = env::var("GITHUB_TOKEN").ok();
+        let github_token = github_token.or_else(|| env::var("GITHUB_TOKEN").ok());
 
-        Ok(ActionConfig {
-            repo: Repo::try_from_env()?,
+        info!("Reading value for GITHUB_SHA");
+        let sha = env::var("GITHUB_SHA").ok();
+
+        info!("Reading value for GITHUB_REF_NAME");
+        let branch = env::var("GITHUB_REF_NAME").ok().or_else(|| {
+            env::var("GITHUB_REF")
+                .ok()
+                .and_then(|r| r.strip_prefix("refs/heads/").map(str::to_string))
+        });
+
+        info!("Reading value for INPUT_DRY_RUN");
+        let dry_run = dry_run
+            || matches!(
+                env::var("INPUT_DRY_RUN").as_deref(),
+                Ok("true") | Ok("True") | Ok("TRUE")
+            );
+
+        ActionConfig {
+            mode: Mode::Release,
+            backend,
+            repo: None,
             workspace: workspace_path,
             configuration_file,
             github_token,
-        })
+            sha,
+            branch,
+            dry_run,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn the_git_backend_reads_up_to_head() {
+        env::remove_var("GITHUB_SHA");
+        let args = ["next-version", "--backend", "git", "--workspace", "repo"];
+        let cli_args = CliArgs::parse(args.iter().map(|arg| arg.to_string())).unwrap();
+
+        let action_config = ActionConfig::try_from_cli(&cli_args).unwrap();
+
+        assert!(matches!(action_config.backend, Backend::Git));
+        assert_eq!(action_config.sha.as_deref(), Some("HEAD"));
+        assert_eq!(action_config.workspace, PathBuf::from("repo"));
     }
 }
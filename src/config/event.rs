@@ -0,0 +1,77 @@
+use eyre::{eyre, Context, Result};
+use log::info;
+use pr_bump_lib::PullRequest;
+use serde::Deserialize;
+use std::{env, fs};
+
+/// The pull request that triggered a `pull_request` workflow run
+pub struct PullRequestEvent {
+    pub pull: PullRequest,
+
+    /// The branch the pull request would be merged into
+    pub base_ref: String,
+
+    /// The commit of the base branch the pull request is compared with
+    pub base_sha: String,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    pull_request: Option<EventPull>,
+}
+
+#[derive(Deserialize)]
+struct EventPull {
+    number: u64,
+    title: String,
+    body: Option<String>,
+    #[serde(default)]
+    labels: Vec<EventLabel>,
+    user: Option<EventUser>,
+    html_url: Option<String>,
+    base: EventBase,
+}
+
+#[derive(Deserialize)]
+struct EventLabel {
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct EventUser {
+    login: String,
+}
+
+#[derive(Deserialize)]
+struct EventBase {
+    #[serde(rename = "ref")]
+    branch: String,
+    sha: String,
+}
+
+impl PullRequestEvent {
+    /// Read the pull request from the event payload in `GITHUB_EVENT_PATH`
+    pub fn try_from_env() -> Result<Self> {
+        info!("Reading value for GITHUB_EVENT_PATH");
+        let path = env::var("GITHUB_EVENT_PATH")
+            .wrap_err("Could not read GITHUB_EVENT_PATH to find the pull request")?;
+        let contents = fs::read_to_string(&path)
+            .wrap_err(format!("Could not read the event payload at '{}'", path))?;
+        let event: Event = serde_json::from_str(&contents)
+            .wrap_err(format!("Could not parse the event payload at '{}'", path))?;
+
+        let pull = event.pull_request.ok_or_else(|| {
+            eyre!("The event has no pull request, run pr-bump on a pull_request event")
+        })?;
+        let labels = pull.labels.into_iter().map(|label| label.name).collect();
+        let mut pull_request = PullRequest::new(pull.number, pull.title, pull.body, labels, None);
+        pull_request.author = pull.user.map(|user| user.login);
+        pull_request.url = pull.html_url;
+
+        Ok(PullRequestEvent {
+            pull: pull_request,
+            base_ref: pull.base.branch,
+            base_sha: pull.base.sha,
+        })
+    }
+}
@@ -1,2 +1,5 @@
 pub mod actions_config;
+pub mod event;
 pub mod pr_bump_config;
+pub mod schema;
+pub mod validation;
@@ -1,16 +1,32 @@
 use std::{
     convert::TryFrom,
+    fmt::{self, Display},
     fs,
+    marker::PhantomData,
     path::{Path, PathBuf},
 };
 
 use eyre::Context;
 use log::info;
-use pr_bump_lib::BumpRules;
-use serde::Deserialize;
+use pr_bump_lib::{
+    BumpRules, Changelog, FileFormat, IgnoreMode, NewRelease, Occurrences, PullFilter,
+    VersionTemplate,
+};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, ObjectValidation, Schema, SchemaObject},
+    schema_for, JsonSchema,
+};
+use semver::Version;
+use serde::{
+    de::{DeserializeOwned, Error, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use super::validation::with_suggestion;
 
 type Label = String;
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SemverPart {
     #[serde(rename = "patch")]
     Patch,
@@ -20,38 +36,394 @@ pub enum SemverPart {
     Major,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullSelection {
+    #[serde(rename = "merged_after_release")]
+    MergedAfterRelease,
+    #[serde(rename = "commit_range")]
+    CommitRange,
+}
+
+/// A part of the configuration that release-changelog-builder can share
+pub trait SharedWithChangelogBuilder: JsonSchema {
+    /// The keys release-changelog-builder reads at this level, which pr-bump ignores
+    const CHANGELOG_BUILDER_KEYS: &'static [&'static str];
+}
+
+/// The keys of release-changelog-builder set in a part of the configuration
+///
+/// This is flattened into the part, so it receives the keys the part does not know. Since
+/// `deny_unknown_fields` does not work with `#[serde(flatten)]`, any key that is not one of
+/// release-changelog-builder's is rejected here instead. The values are ignored, and the keys are
+/// not advertised in the schema, which only tolerates them.
+#[derive(Debug)]
+pub struct ChangelogBuilderKeys<Config> {
+    keys: Vec<String>,
+    config: PhantomData<Config>,
+}
+
+impl<Config> Default for ChangelogBuilderKeys<Config> {
+    fn default() -> Self {
+        ChangelogBuilderKeys {
+            keys: Vec::new(),
+            config: PhantomData,
+        }
+    }
+}
+
+impl<'de, Config: SharedWithChangelogBuilder> Deserialize<'de> for ChangelogBuilderKeys<Config> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries: Vec<(String, IgnoredAny)> = deserializer.deserialize_map(EntriesVisitor)?;
+        let mut keys = Vec::new();
+        for (key, _) in entries {
+            if !Config::CHANGELOG_BUILDER_KEYS.contains(&key.as_str()) {
+                // The same message as `deny_unknown_fields`, so a suggestion can be added
+                let known: Vec<String> = schema_for!(Config)
+                    .schema
+                    .object
+                    .map(|object| object.properties.into_keys().collect())
+                    .unwrap_or_default();
+                return Err(D::Error::custom(format_args!(
+                    "unknown field `{}`, expected one of `{}`",
+                    key,
+                    known.join("`, `")
+                )));
+            }
+            keys.push(key);
+        }
+
+        Ok(ChangelogBuilderKeys {
+            keys,
+            config: PhantomData,
+        })
+    }
+}
+
+/// Read the entries of a map in order
+struct EntriesVisitor;
+
+impl<'de> Visitor<'de> for EntriesVisitor {
+    type Value = Vec<(String, IgnoredAny)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+}
+
+impl<Config: SharedWithChangelogBuilder> JsonSchema for ChangelogBuilderKeys<Config> {
+    fn schema_name() -> String {
+        format!("ChangelogBuilderKeys_for_{}", Config::schema_name())
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let keys = format!("^({})$", Config::CHANGELOG_BUILDER_KEYS.join("|"));
+        let object = ObjectValidation {
+            pattern_properties: vec![(keys, Schema::Bool(true))].into_iter().collect(),
+            additional_properties: Some(Box::new(Schema::Bool(false))),
+            ..Default::default()
+        };
+
+        SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(object)),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct Category {
+    pub title: Option<String>,
     pub labels: Vec<Label>,
     pub semver_part: SemverPart,
+
+    #[serde(flatten)]
+    pub changelog_builder: ChangelogBuilderKeys<Category>,
+}
+
+impl SharedWithChangelogBuilder for Category {
+    const CHANGELOG_BUILDER_KEYS: &'static [&'static str] = &[
+        "exclude_labels",
+        "exhaustive",
+        "exhaustive_rules",
+        "rules",
+        "empty_content",
+        "categories",
+    ];
 }
 
 impl Category {
-    pub fn new(labels: Vec<Label>, semver_part: SemverPart) -> Self {
+    pub fn new(title: &str, labels: Vec<Label>, semver_part: SemverPart) -> Self {
         Category {
+            title: Some(title.to_string()),
             labels,
             semver_part,
+            changelog_builder: ChangelogBuilderKeys::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CommitCategory {
+    pub types: Vec<String>,
+    pub semver_part: SemverPart,
+}
+
+impl CommitCategory {
+    pub fn new(types: Vec<String>, semver_part: SemverPart) -> Self {
+        CommitCategory { types, semver_part }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ConventionalCommits {
+    #[serde(default = "ConventionalCommits::default_categories")]
+    pub categories: Vec<CommitCategory>,
+}
+
+impl ConventionalCommits {
+    fn default_categories() -> Vec<CommitCategory> {
+        vec![
+            CommitCategory::new(
+                vec!["fix".to_string(), "perf".to_string()],
+                SemverPart::Patch,
+            ),
+            CommitCategory::new(vec!["feat".to_string()], SemverPart::Minor),
+        ]
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PrereleaseChannel {
+    pub identifier: String,
+    pub branches: Option<Vec<String>>,
+}
+
+impl PrereleaseChannel {
+    /// Whether this channel is used on the given branch (channels without branches always are)
+    pub fn matches(&self, branch: Option<&str>) -> bool {
+        match (&self.branches, branch) {
+            (None, _) => true,
+            (Some(branches), Some(branch)) => branches.iter().any(|b| b == branch),
+            (Some(_), None) => false,
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct InitialDevelopment {
+    #[serde(default = "InitialDevelopment::default_graduation_labels")]
+    pub graduation_labels: Vec<Label>,
+}
+
+impl InitialDevelopment {
+    fn default_graduation_labels() -> Vec<Label> {
+        vec!["release-1.0".to_string()]
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PublishRelease {
+    #[serde(default = "PublishRelease::default_template")]
+    pub tag_name: String,
+    #[serde(default = "PublishRelease::default_template")]
+    pub name: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub draft: bool,
+    pub prerelease: Option<bool>,
+}
+
+impl PublishRelease {
+    fn default_template() -> String {
+        "v{version}".to_string()
+    }
+
+    fn render(template: &str, version: &Version) -> String {
+        template.replace("{version}", &version.to_string())
+    }
+
+    /// Build the release for a version, replacing `{version}` in the tag name and release name
+    ///
+    /// The changelog is used as the release body, unless a body is configured.
+    pub fn new_release(
+        &self,
+        version: &Version,
+        target_commitish: Option<String>,
+        changelog: &str,
+    ) -> NewRelease {
+        let mut release = NewRelease::new(
+            Self::render(&self.tag_name, version),
+            Self::render(&self.name, version),
+        );
+        release.target_commitish = target_commitish;
+        release.body = match self.body.as_str() {
+            "" => changelog.to_string(),
+            body => body.to_string(),
+        };
+        release.draft = self.draft;
+        release.prerelease = self.prerelease.unwrap_or(!version.pre.is_empty());
+
+        release
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChangelogFile {
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CargoWorkspace {
+    #[serde(default = "CargoWorkspace::default_path")]
+    pub path: PathBuf,
+}
+
+impl CargoWorkspace {
+    fn default_path() -> PathBuf {
+        PathBuf::from("Cargo.toml")
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct BumpFile {
     pub path: PathBuf,
 
     #[serde(default)]
     pub prefix: String,
+
+    pub format: Option<FileFormat>,
+    pub key_path: Option<String>,
+
+    #[serde(default)]
+    pub occurrences: Occurrences,
+
+    pub template: Option<String>,
+
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl BumpFile {
+    /// Find the files to bump in the workspace
+    ///
+    /// The path can be a glob pattern, in which case the matching files that are not excluded are
+    /// bumped and there must be at least one.
+    pub fn paths(&self, workspace: &Path) -> eyre::Result<Vec<PathBuf>> {
+        let path = self.path.to_string_lossy();
+        if !path.contains(['*', '?', '[']) {
+            return Ok(vec![workspace.join(&self.path)]);
+        }
+
+        let excluded = self
+            .exclude
+            .iter()
+            .map(|pattern| glob::Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .wrap_err("Invalid exclude pattern")?;
+
+        let pattern = workspace.join(&self.path);
+        let mut paths = Vec::new();
+        for entry in glob::glob(&pattern.to_string_lossy())
+            .wrap_err(format!("Invalid bump file pattern '{}'", path))?
+        {
+            let entry = entry?;
+            let relative = entry.strip_prefix(workspace).unwrap_or(&entry);
+            if entry.is_file() && !excluded.iter().any(|e| e.matches_path(relative)) {
+                paths.push(entry);
+            }
+        }
+
+        if paths.is_empty() {
+            return Err(eyre::eyre!("No files match '{}'", path));
+        }
+
+        Ok(paths)
+    }
+
+    pub fn template(&self) -> eyre::Result<VersionTemplate> {
+        match &self.template {
+            Some(template) => VersionTemplate::new(template),
+            None => Ok(VersionTemplate::default()),
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, JsonSchema, Debug)]
+#[schemars(deny_unknown_fields)]
 pub struct PrBumpConfig {
+    /// The JSON Schema of the configuration, for editors (it is not used otherwise)
+    #[serde(rename = "$schema")]
+    #[allow(dead_code)]
+    pub schema: Option<String>,
     pub base_branches: Option<Vec<String>>,
     pub bump_files: Option<Vec<BumpFile>>,
+    pub cargo_workspace: Option<CargoWorkspace>,
     pub categories: Option<Vec<Category>>,
     pub ignore_labels: Option<Vec<Label>>,
+    pub ignore_mode: Option<IgnoreMode>,
+    pub include_unlabeled: Option<bool>,
+    pub pull_selection: Option<PullSelection>,
+    pub conventional_commits: Option<ConventionalCommits>,
+    pub prerelease_channels: Option<Vec<PrereleaseChannel>>,
+    pub initial_development: Option<InitialDevelopment>,
+    pub publish_release: Option<PublishRelease>,
+    pub changelog_file: Option<ChangelogFile>,
+
+    #[serde(flatten)]
+    pub changelog_builder: ChangelogBuilderKeys<PrBumpConfig>,
+}
+
+impl SharedWithChangelogBuilder for PrBumpConfig {
+    const CHANGELOG_BUILDER_KEYS: &'static [&'static str] = &[
+        "template",
+        "pr_template",
+        "commit_template",
+        "empty_template",
+        "sort",
+        "label_extractor",
+        "duplicate_filter",
+        "reference",
+        "transformers",
+        "tag_resolver",
+        "custom_placeholders",
+        "trim_values",
+        "max_tags_to_fetch",
+        "max_pull_requests",
+        "max_back_track_time_days",
+        "exclude_merge_branches",
+    ];
 }
 
 impl PrBumpConfig {
+    /// The tag name of a version, from the `publish_release` tag name (`v{version}` by default)
+    pub fn tag_name(&self, version: &Version) -> String {
+        match &self.publish_release {
+            Some(publish_release) => PublishRelease::render(&publish_release.tag_name, version),
+            None => PublishRelease::render(&PublishRelease::default_template(), version),
+        }
+    }
+
     pub fn merge(mut self, other: Self) -> Self {
         if self.base_branches.is_none() {
             self.base_branches = other.base_branches;
@@ -61,6 +433,10 @@ impl PrBumpConfig {
             self.bump_files = other.bump_files
         }
 
+        if self.cargo_workspace.is_none() {
+            self.cargo_workspace = other.cargo_workspace
+        }
+
         if self.categories.is_none() {
             self.categories = other.categories
         }
@@ -69,10 +445,89 @@ impl PrBumpConfig {
             self.ignore_labels = other.ignore_labels
         }
 
+        if self.ignore_mode.is_none() {
+            self.ignore_mode = other.ignore_mode
+        }
+
+        if self.include_unlabeled.is_none() {
+            self.include_unlabeled = other.include_unlabeled
+        }
+
+        if self.pull_selection.is_none() {
+            self.pull_selection = other.pull_selection
+        }
+
+        if self.conventional_commits.is_none() {
+            self.conventional_commits = other.conventional_commits
+        }
+
+        if self.prerelease_channels.is_none() {
+            self.prerelease_channels = other.prerelease_channels
+        }
+
+        if self.initial_development.is_none() {
+            self.initial_development = other.initial_development
+        }
+
+        if self.publish_release.is_none() {
+            self.publish_release = other.publish_release
+        }
+
+        if self.changelog_file.is_none() {
+            self.changelog_file = other.changelog_file
+        }
+
         self
     }
 
-    pub fn get_bump_rules(&self) -> BumpRules {
+    /// Build a changelog with one section for every category that has a title
+    pub fn get_changelog(&self) -> Changelog {
+        let mut changelog = Changelog::new();
+
+        for category in self.categories.iter().flatten() {
+            if let Some(title) = &category.title {
+                changelog.add_section(title.clone(), category.labels.clone());
+            }
+        }
+
+        changelog
+    }
+
+    /// The keys of release-changelog-builder that are set, which pr-bump ignores
+    pub fn changelog_builder_keys(&self) -> Vec<&str> {
+        let category_keys = self
+            .categories
+            .iter()
+            .flatten()
+            .flat_map(|category| category.changelog_builder.keys.iter());
+
+        let mut keys: Vec<&str> = Vec::new();
+        for key in self.changelog_builder.keys.iter().chain(category_keys) {
+            if !keys.contains(&key.as_str()) {
+                keys.push(key);
+            }
+        }
+
+        keys
+    }
+
+    /// The labels that decide what happens to a pull request: category labels, then ignored labels
+    pub fn accepted_labels(&self) -> Vec<&str> {
+        let categories = self.categories.iter().flatten();
+        let category_labels = categories.flat_map(|category| category.labels.iter());
+        let ignored_labels = self.ignore_labels.iter().flatten();
+
+        let mut labels: Vec<&str> = Vec::new();
+        for label in category_labels.chain(ignored_labels) {
+            if !labels.contains(&label.as_str()) {
+                labels.push(label);
+            }
+        }
+
+        labels
+    }
+
+    pub fn get_bump_rules(&self, branch: Option<&str>) -> eyre::Result<BumpRules> {
         let mut rules = BumpRules::new();
 
         if let Some(categories) = self.categories.as_ref() {
@@ -85,37 +540,203 @@ impl PrBumpConfig {
             }
         }
 
-        rules
+        if let Some(conventional_commits) = self.conventional_commits.as_ref() {
+            rules.enable_conventional_commits();
+
+            for category in &conventional_commits.categories {
+                match category.semver_part {
+                    SemverPart::Patch => rules.add_patch_commit_types(category.types.clone()),
+                    SemverPart::Minor => rules.add_minor_commit_types(category.types.clone()),
+                    SemverPart::Major => rules.add_major_commit_types(category.types.clone()),
+                }
+            }
+        }
+
+        rules.set_pull_filter(PullFilter::new(
+            self.ignore_labels.clone().unwrap_or_default(),
+            self.ignore_mode.unwrap_or_default(),
+            self.include_unlabeled.unwrap_or(true),
+        ));
+
+        if let Some(initial_development) = self.initial_development.as_ref() {
+            rules.enable_initial_development();
+            rules.add_graduation_labels(initial_development.graduation_labels.clone());
+        }
+
+        let channel = self
+            .prerelease_channels
+            .iter()
+            .flatten()
+            .find(|channel| channel.matches(branch));
+        if let Some(channel) = channel {
+            info!("Using the '{}' prerelease channel", channel.identifier);
+            rules.set_prerelease(&channel.identifier)?;
+        }
+
+        Ok(rules)
+    }
+}
+
+/// The format of a configuration file, found from its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Json,
+    Json5,
+    Yaml,
+    Toml,
+    CargoManifest,
+}
+
+impl ConfigFormat {
+    fn of(path: &Path) -> Self {
+        if path.file_name().and_then(|name| name.to_str()) == Some("Cargo.toml") {
+            return ConfigFormat::CargoManifest;
+        }
+
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("json5") | Some("jsonc") => ConfigFormat::Json5,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
     }
 }
 
+/// The parts of a Cargo manifest that can hold the configuration
+#[derive(Deserialize)]
+struct CargoManifest<Config> {
+    package: Option<CargoMetadata<Config>>,
+    workspace: Option<CargoMetadata<Config>>,
+}
+
+#[derive(Deserialize)]
+struct CargoMetadata<Config> {
+    metadata: Option<CargoMetadataTable<Config>>,
+}
+
+#[derive(Deserialize)]
+struct CargoMetadataTable<Config> {
+    #[serde(rename = "pr-bump")]
+    pr_bump: Option<Config>,
+}
+
+impl<Config> CargoManifest<Config> {
+    fn into_config(self) -> Option<Config> {
+        let from_metadata = |metadata: Option<CargoMetadata<Config>>| metadata?.metadata?.pr_bump;
+
+        let CargoManifest { package, workspace } = self;
+        from_metadata(package).or_else(|| from_metadata(workspace))
+    }
+}
+
+impl PrBumpConfig {
+    /// Read the configuration in the `[package.metadata.pr-bump]` (or
+    /// `[workspace.metadata.pr-bump]`) table of a Cargo manifest, if there is one
+    pub fn from_cargo_metadata(manifest: &Path) -> eyre::Result<Option<Self>> {
+        if !manifest.is_file() {
+            return Ok(None);
+        }
+
+        let contents = read_config(manifest)?;
+        let manifest: CargoManifest<Self> =
+            toml_edit::de::from_str(&contents).map_err(parse_error)?;
+
+        Ok(manifest.into_config())
+    }
+}
+
+fn read_config(config: &Path) -> eyre::Result<String> {
+    info!("Trying to read config file '{}'", config.to_string_lossy());
+    fs::read_to_string(config).wrap_err(format!(
+        "Could not read configuration at '{}'",
+        config.to_string_lossy()
+    ))
+}
+
+/// Describe an error from parsing the configuration, suggesting a fix for unknown names
+fn parse_error(error: impl Display) -> eyre::Error {
+    eyre::eyre!(with_suggestion(error.to_string()))
+}
+
+/// Describe an error from parsing JSON5, which does not include the location in its message
+fn json5_error(error: json5::Error) -> eyre::Error {
+    match &error {
+        json5::Error::Message {
+            msg,
+            location: Some(location),
+        } => parse_error(format!(
+            "{} at line {} column {}",
+            msg, location.line, location.column
+        )),
+        _ => parse_error(error),
+    }
+}
+
+/// Describe an error from parsing YAML, adding the location when it is not in the message
+fn yaml_error(error: serde_yaml::Error) -> eyre::Error {
+    match error.location() {
+        Some(location) if !error.to_string().contains(" at line ") => parse_error(format!(
+            "{} at line {} column {}",
+            error,
+            location.line(),
+            location.column()
+        )),
+        _ => parse_error(error),
+    }
+}
+
+/// Parse a configuration file, in a format that depends on its extension
+///
+/// JSON is used unless the extension is `.json5`, `.jsonc`, `.yaml`, `.yml` or `.toml`. For
+/// `Cargo.toml`, the configuration is read from its `[package.metadata.pr-bump]` table.
+pub fn parse_config_file<Config: DeserializeOwned>(config: &Path) -> eyre::Result<Config> {
+    let config_content = read_config(config)?;
+
+    let format = ConfigFormat::of(config);
+    info!("Parsing configuration file ({:?})", format);
+    let deserialized_config = match format {
+        ConfigFormat::Json => serde_json::from_str(&config_content).map_err(parse_error)?,
+        ConfigFormat::Json5 => json5::from_str(&config_content).map_err(json5_error)?,
+        ConfigFormat::Yaml => serde_yaml::from_str(&config_content).map_err(yaml_error)?,
+        ConfigFormat::Toml => toml_edit::de::from_str(&config_content).map_err(parse_error)?,
+        ConfigFormat::CargoManifest => {
+            toml_edit::de::from_str::<CargoManifest<Config>>(&config_content)
+                .map_err(parse_error)?
+                .into_config()
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "No [package.metadata.pr-bump] table in '{}'",
+                        config.to_string_lossy()
+                    )
+                })?
+        }
+    };
+
+    Ok(deserialized_config)
+}
+
 type ConfigFile<'a> = &'a Path;
 impl TryFrom<ConfigFile<'_>> for PrBumpConfig {
     type Error = eyre::Error;
 
     fn try_from(config: ConfigFile) -> Result<Self, Self::Error> {
-        info!("Trying to read config file '{}'", config.to_string_lossy());
-        let mut config_content = fs::read_to_string(config).wrap_err(format!(
-            "Could not read configuration at '{}'",
-            config.to_string_lossy()
-        ))?;
-
-        config_content.retain(|c| !c.is_control());
-
-        info!("Parsing configuration file");
-        let deserialized_config = serde_json::from_str(&config_content)?;
-
-        Ok(deserialized_config)
+        parse_config_file(config)
     }
 }
 
 impl Default for PrBumpConfig {
     fn default() -> Self {
         PrBumpConfig {
+            schema: None,
             base_branches: None,
             bump_files: Some(Vec::new()),
+            cargo_workspace: None,
             categories: Some(vec![
                 Category::new(
+                    "## 🐛 Fixes",
                     vec![
                         "bug".to_string(),
                         "docs".to_string(),
@@ -126,6 +747,7 @@ impl Default for PrBumpConfig {
                     SemverPart::Patch,
                 ),
                 Category::new(
+                    "## 🚀 Features",
                     vec![
                         "enhancement".to_string(),
                         "feat".to_string(),
@@ -135,11 +757,117 @@ impl Default for PrBumpConfig {
                     SemverPart::Minor,
                 ),
                 Category::new(
+                    "## 💥 Breaking",
                     vec!["breaking".to_string(), "major".to_string()],
                     SemverPart::Major,
                 ),
             ]),
             ignore_labels: Some(Vec::new()),
+            ignore_mode: Some(IgnoreMode::Any),
+            include_unlabeled: Some(true),
+            pull_selection: Some(PullSelection::MergedAfterRelease),
+            conventional_commits: None,
+            prerelease_channels: Some(Vec::new()),
+            initial_development: None,
+            publish_release: None,
+            changelog_file: None,
+            changelog_builder: ChangelogBuilderKeys::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bump_file(json: &str) -> BumpFile {
+        serde_json::from_str(json).unwrap()
+    }
+
+    /// A workspace with `package.json` files in `a`, `b` and `b/node_modules/c`
+    fn workspace() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for directory in ["a", "b", "b/node_modules/c"] {
+            let directory = dir.path().join(directory);
+            fs::create_dir_all(&directory).unwrap();
+            fs::write(directory.join("package.json"), "{}").unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn a_plain_path_is_used_as_is() {
+        let dir = workspace();
+        let paths = bump_file(r#"{ "path": "missing.json" }"#)
+            .paths(dir.path())
+            .unwrap();
+
+        assert_eq!(paths, vec![dir.path().join("missing.json")]);
+    }
+
+    #[test]
+    fn a_glob_path_finds_the_files_that_are_not_excluded() {
+        let dir = workspace();
+        let paths =
+            bump_file(r#"{ "path": "**/package.json", "exclude": ["**/node_modules/**"] }"#)
+                .paths(dir.path())
+                .unwrap();
+
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("a/package.json"),
+                dir.path().join("b/package.json")
+            ]
+        );
+    }
+
+    #[test]
+    fn a_glob_path_must_match_a_file() {
+        let dir = workspace();
+        let error = bump_file(r#"{ "path": "*/Cargo.toml" }"#)
+            .paths(dir.path())
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "No files match '*/Cargo.toml'");
+    }
+
+    #[test]
+    fn release_changelog_builder_keys_are_ignored() {
+        let config: PrBumpConfig = serde_json::from_str(
+            r#"{
+                "template": "{{CHANGELOG}}",
+                "categories": [
+                    { "labels": ["fix"], "semver_part": "patch", "rules": [], "categories": [] }
+                ],
+                "sort": { "order": "ASC" }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.changelog_builder_keys(),
+            vec!["template", "sort", "rules", "categories"]
+        );
+    }
+
+    #[test]
+    fn other_unknown_keys_are_refused_with_a_suggestion() {
+        for (contents, unknown) in [
+            (r#"{ "ignored_labels": [] }"#, "ignore_labels"),
+            (
+                r#"{ "categories": [{ "labels": [], "semver_part": "patch", "titel": "" }] }"#,
+                "title",
+            ),
+        ] {
+            let error = serde_json::from_str::<PrBumpConfig>(contents)
+                .map_err(parse_error)
+                .unwrap_err();
+
+            assert!(error
+                .to_string()
+                .ends_with(&format!("(did you mean `{}`?)", unknown)));
         }
     }
 }
@@ -0,0 +1,54 @@
+use eyre::{eyre, Result};
+use jsonschema::JSONSchema;
+use schemars::schema_for;
+use serde_json::Value;
+use std::path::Path;
+
+use super::pr_bump_config::{parse_config_file, PrBumpConfig};
+
+/// The JSON Schema of the configuration, generated from the configuration types
+pub fn config_schema() -> Value {
+    serde_json::to_value(schema_for!(PrBumpConfig)).expect("The schema is always valid JSON")
+}
+
+/// Check a configuration file (in any supported format) against the JSON Schema
+///
+/// Every mismatch is reported with the path to the value in the configuration.
+pub fn check_config_file(config: &Path) -> Result<()> {
+    let instance: Value = parse_config_file(config)?;
+    let schema = config_schema();
+    let compiled =
+        JSONSchema::compile(&schema).map_err(|e| eyre!("Invalid configuration schema: {}", e))?;
+
+    let mismatches: Vec<String> = match compiled.validate(&instance) {
+        Ok(()) => return Ok(()),
+        Err(errors) => errors
+            .map(|error| match error.instance_path.to_string().as_str() {
+                "" => format!("/: {}", error),
+                path => format!("{}: {}", path, error),
+            })
+            .collect(),
+    };
+
+    Err(eyre!(
+        "'{}' does not match the configuration schema:\n{}",
+        config.to_string_lossy(),
+        mismatches.join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_committed_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&config_schema()).unwrap();
+
+        assert_eq!(
+            format!("{}\n", schema),
+            include_str!("../../config.schema.json"),
+            "Regenerate it with `pr_bump_bin schema > config.schema.json`"
+        );
+    }
+}
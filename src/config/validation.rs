@@ -0,0 +1,304 @@
+use eyre::{eyre, Result};
+use regex::Regex;
+use std::{collections::HashMap, path::Path};
+
+use super::pr_bump_config::{PrBumpConfig, SemverPart};
+
+/// A problem found in the configuration
+///
+/// The subject is what the problem is about (a label or a path), and its `nth` occurrence after
+/// the section's key is searched for in the configuration file to point at where the problem is.
+struct Problem {
+    message: String,
+    section: &'static str,
+    subject: String,
+    nth: usize,
+}
+
+impl Problem {
+    fn new(message: String, section: &'static str, subject: &str, nth: usize) -> Self {
+        Problem {
+            message,
+            section,
+            subject: subject.to_string(),
+            nth,
+        }
+    }
+}
+
+fn part_name(part: SemverPart) -> &'static str {
+    match part {
+        SemverPart::Patch => "patch",
+        SemverPart::Minor => "minor",
+        SemverPart::Major => "major",
+    }
+}
+
+/// Check that the configuration makes sense, beyond being well-formed
+///
+/// Every problem is reported at once, with its line and column in the configuration file when it
+/// can be found there.
+pub fn validate(
+    config: &PrBumpConfig,
+    workspace: &Path,
+    config_file: Option<(&Path, &str)>,
+) -> Result<()> {
+    let mut problems = Vec::new();
+    let categories = config.categories.as_deref().unwrap_or_default();
+    let ignored = config.ignore_labels.as_deref().unwrap_or_default();
+
+    let mut parts: HashMap<&str, SemverPart> = HashMap::new();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    for category in categories {
+        if category.labels.is_empty() {
+            let title = category.title.as_deref().unwrap_or_default();
+            problems.push(Problem::new(
+                format!(
+                    "A {} category{} has no labels",
+                    part_name(category.semver_part),
+                    match title {
+                        "" => String::new(),
+                        title => format!(" ('{}')", title),
+                    }
+                ),
+                "categories",
+                title,
+                0,
+            ));
+        }
+
+        for label in &category.labels {
+            let nth = occurrences.entry(label).or_insert(0);
+            *nth += 1;
+
+            match parts.insert(label, category.semver_part) {
+                Some(part) if part != category.semver_part => problems.push(Problem::new(
+                    format!(
+                        "The label '{}' is in both a {} and a {} category",
+                        label,
+                        part_name(part),
+                        part_name(category.semver_part)
+                    ),
+                    "categories",
+                    label,
+                    *nth - 1,
+                )),
+                _ => {}
+            }
+
+            if *nth == 1 && ignored.contains(label) {
+                problems.push(Problem::new(
+                    format!(
+                        "The label '{}' is in a category, but it is also ignored",
+                        label
+                    ),
+                    "ignore_labels",
+                    label,
+                    0,
+                ));
+            }
+        }
+    }
+
+    for bump_file in config.bump_files.iter().flatten() {
+        let subject = bump_file.path.to_string_lossy();
+        match bump_file.paths(workspace) {
+            Err(e) => problems.push(Problem::new(
+                format!("Bump file: {}", e),
+                "bump_files",
+                &subject,
+                0,
+            )),
+            Ok(paths) => {
+                for path in paths.iter().filter(|path| !path.is_file()) {
+                    problems.push(Problem::new(
+                        format!(
+                            "The bump file '{}' does not exist",
+                            path.strip_prefix(workspace)
+                                .unwrap_or(path)
+                                .to_string_lossy()
+                        ),
+                        "bump_files",
+                        &subject,
+                        0,
+                    ));
+                }
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let lines: Vec<String> = problems
+        .iter()
+        .map(|problem| match config_file {
+            Some((path, contents)) => match locate(contents, problem) {
+                Some((line, column)) => format!(
+                    "{}:{}:{}: {}",
+                    path.to_string_lossy(),
+                    line,
+                    column,
+                    problem.message
+                ),
+                None => problem.message.clone(),
+            },
+            None => problem.message.clone(),
+        })
+        .collect();
+
+    Err(eyre!(
+        "Found {} problem(s) in the configuration:\n{}",
+        lines.len(),
+        lines.join("\n")
+    ))
+}
+
+/// Find the line and column of a problem's subject in the configuration file
+fn locate(contents: &str, problem: &Problem) -> Option<(usize, usize)> {
+    if problem.subject.is_empty() {
+        return None;
+    }
+
+    let section = section_start(contents, problem.section)?;
+    let find_nth = |pattern: &str| {
+        contents[section..]
+            .match_indices(pattern)
+            .nth(problem.nth)
+            .map(|(index, _)| section + index)
+    };
+    let subject = &problem.subject;
+    let position = find_nth(&format!("\"{}\"", subject))
+        .or_else(|| find_nth(&format!("'{}'", subject)))
+        .or_else(|| find_nth(subject))?;
+
+    let before = &contents[..position];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+
+    Some((line, column))
+}
+
+/// Find where a section starts, which is where its name is used as a key (`"name":` in JSON,
+/// `name:` in YAML, `name =` or `[name]` in TOML)
+///
+/// The name can also be a nested key (categories have a `categories` key for
+/// release-changelog-builder) or appear in a value, so the least indented key is used. When the
+/// name is never at the start of a line, its first occurrence is used.
+fn section_start(contents: &str, section: &str) -> Option<usize> {
+    let re = Regex::new(&format!(
+        r#"(?m)^([ \t]*)(?:["']?{0}["']?[ \t]*[:=]|\[\[?{0}\]\]?)"#,
+        regex::escape(section)
+    ))
+    .unwrap();
+
+    re.captures_iter(contents)
+        .filter_map(|captures| Some((captures.get(1)?.as_str().len(), captures.get(0)?.start())))
+        .min_by_key(|(indent, _)| *indent)
+        .map(|(_, start)| start)
+        .or_else(|| contents.find(section))
+}
+
+/// Add a suggestion to an error about an unknown field or value, when one is close enough
+pub fn with_suggestion(message: String) -> String {
+    let re =
+        Regex::new(r"unknown (?:field|variant) `(?P<unknown>[^`]*)`, expected (?P<expected>.*)")
+            .unwrap();
+    let suggestion = re.captures(&message).and_then(|captures| {
+        let unknown = captures.name("unknown")?.as_str();
+        let expected = captures.name("expected")?.as_str();
+
+        expected
+            .split('`')
+            .skip(1)
+            .step_by(2)
+            .map(|candidate| (distance(unknown, candidate), candidate))
+            .filter(|(distance, candidate)| *distance <= (candidate.len() / 3).max(2))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate.to_string())
+    });
+
+    match suggestion {
+        Some(suggestion) => format!("{} (did you mean `{}`?)", message.trim_end(), suggestion),
+        None => message,
+    }
+}
+
+/// Edit distance between two strings, where `-` and `_` are the same
+fn distance(a: &str, b: &str) -> usize {
+    let normalize = |c: char| {
+        if c == '-' {
+            '_'
+        } else {
+            c.to_ascii_lowercase()
+        }
+    };
+    let a: Vec<char> = a.chars().map(normalize).collect();
+    let b: Vec<char> = b.chars().map(normalize).collect();
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(contents: &str) -> String {
+        let config: PrBumpConfig = serde_json::from_str(contents).unwrap();
+        validate(
+            &config,
+            Path::new("."),
+            Some((Path::new("c.json"), contents)),
+        )
+        .unwrap_err()
+        .to_string()
+    }
+
+    #[test]
+    fn problems_point_at_the_section_key_and_not_another_mention() {
+        let contents = r#"{
+  "categories": [
+    {
+      "title": "Not released, see ignore_labels",
+      "labels": ["skip"],
+      "semver_part": "patch",
+      "categories": []
+    }
+  ],
+  "ignore_labels": ["skip"]
+}"#;
+
+        assert_eq!(
+            problems(contents),
+            "Found 1 problem(s) in the configuration:\n\
+             c.json:10:21: The label 'skip' is in a category, but it is also ignored"
+        );
+    }
+
+    #[test]
+    fn a_label_in_two_categories_points_at_its_second_use() {
+        let contents = r#"{
+  "categories": [
+    { "labels": ["fix"], "semver_part": "patch" },
+    { "labels": ["fix"], "semver_part": "minor" }
+  ]
+}"#;
+
+        assert_eq!(
+            problems(contents),
+            "Found 1 problem(s) in the configuration:\n\
+             c.json:4:18: The label 'fix' is in both a patch and a minor category"
+        );
+    }
+}
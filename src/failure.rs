@@ -0,0 +1,47 @@
+use std::fmt::{self, Display};
+
+use eyre::Report;
+
+/// The class of a failed run, which determines the exit code of the action
+///
+/// Errors are classified by wrapping them with a `Failure` (for example,
+/// `.wrap_err(Failure::Config)`), and errors that were not classified exit with code 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    Config,
+    GitHub,
+    Version,
+    File,
+    Label,
+}
+
+impl Failure {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Config => 2,
+            Failure::GitHub => 3,
+            Failure::Version => 4,
+            Failure::File => 5,
+            Failure::Label => 6,
+        }
+    }
+
+    /// Find the class of an error, if it was classified
+    pub fn of(report: &Report) -> Option<Self> {
+        report.downcast_ref::<Failure>().copied()
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Failure::Config => "Invalid configuration",
+            Failure::GitHub => "Could not communicate with GitHub",
+            Failure::Version => "Could not handle the version",
+            Failure::File => "Could not update files",
+            Failure::Label => "The pull request is missing a label",
+        };
+
+        f.write_str(message)
+    }
+}
@@ -0,0 +1,302 @@
+use eyre::{eyre, Context, Result};
+use log::{error, info, warn};
+use similar::TextDiff;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A change to the contents of a file, computed in memory before anything is written
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: PathBuf,
+
+    /// The contents before the change, or `None` when the file does not exist yet
+    pub before: Option<String>,
+    pub after: String,
+}
+
+impl FileChange {
+    pub fn new(path: PathBuf, before: Option<String>, after: String) -> Self {
+        FileChange {
+            path,
+            before,
+            after,
+        }
+    }
+
+    /// Whether writing the change would modify the file
+    pub fn is_modification(&self) -> bool {
+        self.before.as_deref() != Some(self.after.as_str())
+    }
+
+    /// Render the change as a unified diff, with paths relative to `root`
+    pub fn unified_diff(&self, root: &Path) -> String {
+        let path = self.path.strip_prefix(root).unwrap_or(&self.path);
+        let before_header = match self.before {
+            Some(_) => format!("a/{}", path.to_string_lossy()),
+            None => "/dev/null".to_string(),
+        };
+        let after_header = format!("b/{}", path.to_string_lossy());
+
+        TextDiff::from_lines(self.before.as_deref().unwrap_or_default(), &self.after)
+            .unified_diff()
+            .header(&before_header, &after_header)
+            .to_string()
+    }
+
+    /// Make sure the file on disk is still what the change was computed from
+    fn validate(&self) -> Result<()> {
+        let on_disk = if self.path.exists() {
+            Some(fs::read_to_string(&self.path)?)
+        } else {
+            None
+        };
+        if on_disk != self.before {
+            return Err(eyre!("The file changed since it was read"));
+        }
+
+        match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() && !parent.is_dir() => {
+                Err(eyre!("'{}' is not a directory", parent.to_string_lossy()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Put the file back the way it was before the change
+    fn restore(&self) -> Result<()> {
+        match &self.before {
+            Some(before) => {
+                let temporary = stage_contents(&self.path, before)?;
+                fs::rename(&temporary, &self.path).map_err(|e| {
+                    fs::remove_file(&temporary).ok();
+                    e.into()
+                })
+            }
+            None => fs::remove_file(&self.path).map_err(|e| e.into()),
+        }
+    }
+}
+
+/// Write contents to a temporary file next to the destination, keeping its permissions
+///
+/// The temporary file is in the same directory so it can be renamed over the destination.
+fn stage_contents(destination: &Path, contents: &str) -> Result<PathBuf> {
+    let name = destination
+        .file_name()
+        .ok_or_else(|| eyre!("'{}' is not a file", destination.to_string_lossy()))?;
+    let temporary = destination.with_file_name(format!(".{}.pr-bump.tmp", name.to_string_lossy()));
+
+    let written = fs::write(&temporary, contents).and_then(|_| match fs::metadata(destination) {
+        Ok(metadata) => fs::set_permissions(&temporary, metadata.permissions()),
+        Err(_) => Ok(()),
+    });
+    if let Err(e) = written {
+        fs::remove_file(&temporary).ok();
+        return Err(e.into());
+    }
+
+    Ok(temporary)
+}
+
+/// Changes to several files, computed before any of them is written
+///
+/// Files are read through the pending changes, so the same file can be changed more than once
+/// (for example by two bump files) and the changes add up.
+#[derive(Debug, Default)]
+pub struct FileChanges {
+    changes: Vec<FileChange>,
+}
+
+impl FileChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a change, merging it with a pending change to the same file
+    pub fn add(&mut self, change: FileChange) {
+        match self.changes.iter_mut().find(|c| c.path == change.path) {
+            Some(pending) => pending.after = change.after,
+            None => self.changes.push(change),
+        }
+    }
+
+    /// The changes that modify their file
+    pub fn modifications(&self) -> impl Iterator<Item = &FileChange> {
+        self.changes
+            .iter()
+            .filter(|change| change.is_modification())
+    }
+
+    /// Render every modification as a unified diff, with paths relative to `root`
+    pub fn unified_diff(&self, root: &Path) -> String {
+        self.modifications()
+            .map(|change| change.unified_diff(root))
+            .collect()
+    }
+
+    /// Write every modification, or none of them
+    ///
+    /// All files are validated and their new contents are written to temporary files first, which
+    /// are then renamed over the originals. If a file cannot be renamed, the files that were
+    /// already written are restored. What happened to each file is logged.
+    pub fn write(&self) -> Result<()> {
+        self.write_with(|from, to| fs::rename(from, to))
+    }
+
+    /// Write every modification or none of them, renaming the temporary files with `rename`
+    fn write_with(&self, rename: impl Fn(&Path, &Path) -> io::Result<()>) -> Result<()> {
+        let changes: Vec<&FileChange> = self.modifications().collect();
+
+        for change in &changes {
+            change.validate().wrap_err(format!(
+                "Could not write '{}'",
+                change.path.to_string_lossy()
+            ))?;
+        }
+
+        let mut staged = Vec::new();
+        for change in &changes {
+            match stage_contents(&change.path, &change.after) {
+                Ok(temporary) => staged.push(temporary),
+                Err(e) => {
+                    staged.iter().for_each(|t| {
+                        fs::remove_file(t).ok();
+                    });
+                    return Err(e).wrap_err(format!(
+                        "Could not write '{}', no file was changed",
+                        change.path.to_string_lossy()
+                    ));
+                }
+            }
+        }
+
+        for (index, (change, temporary)) in changes.iter().zip(&staged).enumerate() {
+            if let Err(e) = rename(temporary, &change.path) {
+                staged[index..].iter().for_each(|t| {
+                    fs::remove_file(t).ok();
+                });
+                let outcome = if rollback(&changes[..index], &changes[index..]) {
+                    "the files that were written were restored"
+                } else {
+                    "some files could not be restored"
+                };
+
+                return Err(e).wrap_err(format!(
+                    "Could not write '{}', {}",
+                    change.path.to_string_lossy(),
+                    outcome
+                ));
+            }
+        }
+
+        for change in changes {
+            info!("Wrote '{}'", change.path.to_string_lossy());
+        }
+
+        Ok(())
+    }
+
+    /// Read the contents of a file with its pending changes, or `None` if it does not exist
+    pub(crate) fn read(&self, path: &Path) -> Result<Option<String>> {
+        if let Some(pending) = self.changes.iter().find(|c| c.path == path) {
+            return Ok(Some(pending.after.clone()));
+        }
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        fs::read_to_string(path)
+            .map(Some)
+            .wrap_err(format!("Could not read '{}'", path.to_string_lossy()))
+    }
+
+    /// Read the contents of a file that must exist, with its pending changes
+    pub(crate) fn read_existing(&self, path: &Path) -> Result<String> {
+        self.read(path)?
+            .ok_or_else(|| eyre!("Could not find '{}'", path.to_string_lossy()))
+    }
+}
+
+/// Restore the files that were written and report what happened to every file, returning whether
+/// all of them were restored
+fn rollback(written: &[&FileChange], not_written: &[&FileChange]) -> bool {
+    let mut restored = true;
+    for change in written {
+        match change.restore() {
+            Ok(()) => warn!("Restored '{}'", change.path.to_string_lossy()),
+            Err(e) => {
+                restored = false;
+                error!(
+                    "Could not restore '{}': {}",
+                    change.path.to_string_lossy(),
+                    e
+                );
+            }
+        }
+    }
+
+    for change in not_written {
+        warn!("Did not write '{}'", change.path.to_string_lossy());
+    }
+
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn written_files_are_restored_when_a_rename_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.txt");
+        let second = dir.path().join("second.txt");
+        let created = dir.path().join("created.txt");
+        fs::write(&first, "first 1.0.0\n").unwrap();
+        fs::write(&second, "second 1.0.0\n").unwrap();
+
+        let mut changes = FileChanges::new();
+        for (path, before) in [
+            (&first, Some("first")),
+            (&created, None),
+            (&second, Some("second")),
+        ] {
+            changes.add(FileChange::new(
+                path.clone(),
+                before.map(|before| format!("{} 1.0.0\n", before)),
+                "1.1.0\n".to_string(),
+            ));
+        }
+
+        let renamed = std::cell::Cell::new(0);
+        let error = changes
+            .write_with(|from, to| {
+                renamed.set(renamed.get() + 1);
+                match renamed.get() {
+                    3 => Err(io::Error::other("disk full")),
+                    _ => fs::rename(from, to),
+                }
+            })
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Could not write '{}', the files that were written were restored",
+                second.to_string_lossy()
+            )
+        );
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first 1.0.0\n");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second 1.0.0\n");
+        assert!(!created.exists());
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, vec!["first.txt", "second.txt"]);
+    }
+}
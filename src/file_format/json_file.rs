@@ -0,0 +1,181 @@
+use eyre::{eyre, Result};
+use std::ops::Range;
+
+/// Find the span of the string at `path` (without its quotes)
+pub fn find_value(contents: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    let mut scanner = Scanner {
+        bytes: contents.as_bytes(),
+        position: 0,
+    };
+
+    scanner.find(path)
+}
+
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Scanner<'_> {
+    fn find(&mut self, path: &[String]) -> Result<Option<Range<usize>>> {
+        self.skip_whitespace();
+
+        let key = match path.first() {
+            Some(key) => key,
+            None => {
+                return match self.peek() {
+                    Some(b'"') => {
+                        let span = self.string()?;
+                        Ok(Some(span.start + 1..span.end - 1))
+                    }
+                    _ => Err(eyre!("The value at byte {} is not a string", self.position)),
+                }
+            }
+        };
+
+        match self.peek() {
+            Some(b'{') => {
+                self.position += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b'}') {
+                        return Ok(None);
+                    }
+
+                    let span = self.string()?;
+                    let name: String = serde_json::from_slice(&self.bytes[span])?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+
+                    if &name == key {
+                        return self.find(&path[1..]);
+                    }
+                    self.skip_value()?;
+
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => return Ok(None),
+                    }
+                }
+            }
+            Some(b'[') => {
+                let index = match key.parse::<usize>() {
+                    Ok(index) => index,
+                    Err(_) => return Ok(None),
+                };
+
+                self.position += 1;
+                let mut current = 0;
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() == Some(b']') {
+                        return Ok(None);
+                    }
+                    if current == index {
+                        return self.find(&path[1..]);
+                    }
+                    self.skip_value()?;
+                    current += 1;
+
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.position += 1,
+                        _ => return Ok(None),
+                    }
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        if self.peek() != Some(byte) {
+            return Err(eyre!(
+                "Invalid JSON, expected '{}' at byte {}",
+                byte as char,
+                self.position
+            ));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(
+            self.peek(),
+            Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r')
+        ) {
+            self.position += 1;
+        }
+    }
+
+    /// Read a string, returning its span including the quotes
+    fn string(&mut self) -> Result<Range<usize>> {
+        let start = self.position;
+        self.expect(b'"')?;
+
+        loop {
+            match self.peek() {
+                Some(b'\\') => self.position += 2,
+                Some(b'"') => {
+                    self.position += 1;
+                    return Ok(start..self.position);
+                }
+                Some(_) => self.position += 1,
+                None => return Err(eyre!("Invalid JSON, unterminated string at byte {}", start)),
+            }
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<()> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'"') => {
+                self.string()?;
+            }
+            Some(b'{') | Some(b'[') => {
+                let mut depth = 0;
+                loop {
+                    match self.peek() {
+                        Some(b'"') => {
+                            self.string()?;
+                            continue;
+                        }
+                        Some(b'{') | Some(b'[') => depth += 1,
+                        Some(b'}') | Some(b']') => depth -= 1,
+                        Some(_) => {}
+                        None => return Err(eyre!("Invalid JSON, unexpected end of file")),
+                    }
+
+                    self.position += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',')
+                        | Some(b'}')
+                        | Some(b']')
+                        | Some(b' ')
+                        | Some(b'\n')
+                        | Some(b'\r')
+                        | Some(b'\t')
+                ) {
+                    self.position += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
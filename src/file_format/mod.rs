@@ -0,0 +1,228 @@
+use eyre::{eyre, Result};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::ops::Range;
+
+mod json_file;
+mod toml_file;
+mod xml_file;
+mod yaml_file;
+
+/// A structured file format in which a single field holds the version
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    Toml,
+    Json,
+    Yaml,
+    Xml,
+}
+
+impl FileFormat {
+    /// The key path used when none is configured, which is where the version usually lives
+    pub fn default_key_path(&self) -> &'static str {
+        match self {
+            FileFormat::Toml => "package.version",
+            FileFormat::Json => "$.version",
+            FileFormat::Yaml => "version",
+            FileFormat::Xml => "project.version",
+        }
+    }
+
+    /// Replace the version in the field at `key_path`, leaving the rest of the file untouched
+    ///
+    /// The field must currently hold `current_version`, otherwise an error is returned.
+    pub fn replace_version(
+        &self,
+        contents: &str,
+        key_path: &str,
+        current_version: &str,
+        next_version: &str,
+    ) -> Result<String> {
+        let path = parse_key_path(key_path);
+        if path.is_empty() {
+            return Err(eyre!("The key path '{}' is empty", key_path));
+        }
+
+        let span = match self {
+            FileFormat::Toml => toml_file::find_value(contents, &path)?,
+            FileFormat::Json => json_file::find_value(contents, &path)?,
+            FileFormat::Yaml => yaml_file::find_value(contents, &path)?,
+            FileFormat::Xml => xml_file::find_value(contents, &path)?,
+        };
+        let span = span
+            .filter(|span| contents.get(span.clone()).is_some())
+            .ok_or_else(|| eyre!("No value found at '{}'", key_path))?;
+
+        replace_span(contents, span, current_version, next_version).map_err(|found| {
+            eyre!(
+                "Expected '{}' to be '{}', but found '{}'",
+                key_path,
+                current_version,
+                found
+            )
+        })
+    }
+}
+
+/// Split a key path like `package.version`, `$.version` or `$.packages[0].version` into keys
+fn parse_key_path(key_path: &str) -> Vec<String> {
+    let key_path = key_path.trim();
+    let key_path = key_path.strip_prefix('$').unwrap_or(key_path);
+
+    key_path
+        .replace('[', ".")
+        .replace(']', "")
+        .split('.')
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Replace the current version at the start of a span, or return the text found in the span
+fn replace_span(
+    contents: &str,
+    span: Range<usize>,
+    current_version: &str,
+    next_version: &str,
+) -> Result<String, String> {
+    let found = &contents[span.clone()];
+    if found.trim() != current_version {
+        return Err(found.to_string());
+    }
+
+    let start = span.start + found.find(current_version).unwrap_or_default();
+    let end = start + current_version.len();
+
+    Ok(format!(
+        "{}{}{}",
+        &contents[..start],
+        next_version,
+        &contents[end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toml_empty_string_is_a_mismatch() {
+        let error = FileFormat::Toml
+            .replace_version(
+                "[package]\nversion = \"\"\n",
+                "package.version",
+                "1.0.0",
+                "1.1.0",
+            )
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Expected 'package.version' to be '1.0.0', but found ''"
+        );
+    }
+
+    #[test]
+    fn toml_replaces_in_every_kind_of_string() {
+        for value in ["\"1.0.0\"", "'1.0.0'", "\"\"\"1.0.0\"\"\"", "'''1.0.0'''"] {
+            let contents = format!("version = {}\n", value);
+            let replaced = FileFormat::Toml
+                .replace_version(&contents, "version", "1.0.0", "1.1.0")
+                .unwrap();
+
+            assert_eq!(
+                replaced,
+                format!("version = {}\n", value.replace("1.0.0", "1.1.0"))
+            );
+        }
+    }
+
+    /// Replace the version and check that nothing else changed
+    fn assert_round_trip(format: FileFormat, key_path: &str, contents: &str) {
+        let replaced = format
+            .replace_version(contents, key_path, "1.0.0", "1.1.0")
+            .unwrap();
+        assert_ne!(replaced, contents);
+
+        let restored = format
+            .replace_version(&replaced, key_path, "1.1.0", "1.0.0")
+            .unwrap();
+        assert_eq!(restored, contents);
+    }
+
+    #[test]
+    fn json_keeps_the_formatting() {
+        let contents = "{\n  \"name\" :  \"app\",\n\t\"packages\": [\n    {\"version\": \"0.1.0\"},\n    { \"version\":\"1.0.0\" }\n  ],\n  \"version\": \"1.0.0\"\n}\n";
+
+        assert_eq!(
+            FileFormat::Json
+                .replace_version(contents, "$.packages[1].version", "1.0.0", "1.1.0")
+                .unwrap(),
+            contents.replacen("\"version\":\"1.0.0\"", "\"version\":\"1.1.0\"", 1)
+        );
+        assert_round_trip(FileFormat::Json, "$.version", contents);
+    }
+
+    #[test]
+    fn yaml_keeps_the_comments_and_formatting() {
+        let contents = "# The application\nname: app   # its name\n\ndependencies:\n  other: 1.0.0\npackages:\n- name: first\n  version: '1.0.0' # quoted\n-   name: second\n    # still the second package\n    version:   \"1.0.0\"\nversion: 1.0.0\n";
+
+        assert_eq!(
+            FileFormat::Yaml
+                .replace_version(contents, "packages.1.version", "1.0.0", "1.1.0")
+                .unwrap(),
+            contents.replace("\"1.0.0\"", "\"1.1.0\"")
+        );
+        assert_eq!(
+            FileFormat::Yaml
+                .replace_version(contents, "version", "1.0.0", "1.1.0")
+                .unwrap(),
+            contents.replace("\nversion: 1.0.0", "\nversion: 1.1.0")
+        );
+        assert_round_trip(FileFormat::Yaml, "packages[0].version", contents);
+        assert_round_trip(FileFormat::Yaml, "dependencies.other", contents);
+    }
+
+    #[test]
+    fn yaml_reads_the_first_document() {
+        let contents = "---\nversion: 1.0.0\n---\nversion: 2.0.0\n";
+
+        assert_eq!(
+            FileFormat::Yaml
+                .replace_version(contents, "version", "1.0.0", "1.1.0")
+                .unwrap(),
+            "---\nversion: 1.1.0\n---\nversion: 2.0.0\n"
+        );
+    }
+
+    #[test]
+    fn yaml_refuses_values_it_cannot_locate() {
+        for contents in [
+            "base: &v 1.0.0\nversion: *v\n",
+            "package: {version: 1.0.0}\n",
+        ] {
+            let key_path = match contents.starts_with("base") {
+                true => "version",
+                false => "package.version",
+            };
+
+            assert!(FileFormat::Yaml
+                .replace_version(contents, key_path, "1.0.0", "1.1.0")
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn xml_keeps_the_comments_and_formatting() {
+        let contents = "<?xml version=\"1.0\"?>\n<project>\n  <!-- The version is bumped on release -->\n  <version>1.0.0</version>\n  <dependencies>\n    <dependency><version>1.0.0</version></dependency>\n  </dependencies>\n</project>\n";
+
+        assert_eq!(
+            FileFormat::Xml
+                .replace_version(contents, "project.version", "1.0.0", "1.1.0")
+                .unwrap(),
+            contents.replacen("1.0.0</version>", "1.1.0</version>", 1)
+        );
+        assert_round_trip(FileFormat::Xml, "project.version", contents);
+    }
+}
@@ -0,0 +1,46 @@
+use eyre::{eyre, Context, Result};
+use std::ops::Range;
+use toml_edit::{ImDocument, Item};
+
+/// Find the span of the string at `path` (without its quotes)
+pub fn find_value(contents: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    let document = ImDocument::parse(contents).wrap_err("Could not parse TOML")?;
+
+    let mut item: &Item = document.as_item();
+    for key in path {
+        let next = match key.parse::<usize>() {
+            Ok(index) if item.is_array() => item.get(index),
+            _ => item.get(key.as_str()),
+        };
+        item = match next {
+            Some(next) => next,
+            None => return Ok(None),
+        };
+    }
+
+    if !item.is_str() {
+        return Err(eyre!("The value at '{}' is not a string", path.join(".")));
+    }
+
+    match item.span() {
+        Some(span) => strip_quotes(contents, span).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Remove the quotes around a string's raw span, which are tripled for multi-line strings
+fn strip_quotes(contents: &str, span: Range<usize>) -> Result<Range<usize>> {
+    let raw = contents
+        .get(span.clone())
+        .ok_or_else(|| eyre!("The string's span is outside of the file"))?;
+
+    let quotes = ["\"\"\"", "'''", "\"", "'"]
+        .iter()
+        .find(|quote| {
+            raw.len() >= quote.len() * 2 && raw.starts_with(*quote) && raw.ends_with(*quote)
+        })
+        .map(|quote| quote.len())
+        .ok_or_else(|| eyre!("Expected a quoted string, but found '{}'", raw))?;
+
+    Ok(span.start + quotes..span.end - quotes)
+}
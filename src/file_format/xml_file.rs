@@ -0,0 +1,46 @@
+use eyre::{eyre, Result};
+use roxmltree::{Document, Node};
+use std::ops::Range;
+
+/// Find the span of the text inside the element at `path`
+///
+/// The first key is the name of the root element, so `project.version` is the `<version>` element
+/// directly under `<project>`. Numeric keys select the n-th element with the previous name.
+pub fn find_value(contents: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    let document = Document::parse(contents).map_err(|e| eyre!("Could not parse XML: {}", e))?;
+
+    let root = document.root_element();
+    if root.tag_name().name() != path[0] {
+        return Ok(None);
+    }
+
+    let mut node = root;
+    let mut keys = path[1..].iter().peekable();
+    while let Some(key) = keys.next() {
+        let index = match keys.peek().map(|k| k.parse::<usize>()) {
+            Some(Ok(index)) => {
+                keys.next();
+                index
+            }
+            _ => 0,
+        };
+
+        node = match children_named(node, key).nth(index) {
+            Some(child) => child,
+            None => return Ok(None),
+        };
+    }
+
+    Ok(node
+        .children()
+        .find(|child| child.is_text())
+        .map(|text| text.range()))
+}
+
+fn children_named<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
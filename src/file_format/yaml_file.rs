@@ -0,0 +1,229 @@
+use eyre::{eyre, Result};
+use serde::Deserialize;
+use serde_yaml::Value;
+use std::ops::Range;
+
+/// Find the span of the scalar at `path` in the first YAML document (without its quotes)
+///
+/// The document is parsed with serde_yaml to find the value, and its span is then found by
+/// scanning the block style lines. Values that cannot be located in the text this way, like flow
+/// collections, block scalars or aliases, are an error rather than a guess.
+pub fn find_value(contents: &str, path: &[String]) -> Result<Option<Range<usize>>> {
+    let document = match serde_yaml::Deserializer::from_str(contents).next() {
+        Some(document) => {
+            Value::deserialize(document).map_err(|e| eyre!("Could not parse YAML: {}", e))?
+        }
+        None => return Ok(None),
+    };
+
+    let expected = match lookup(&document, path) {
+        Some(Value::String(value)) => value.clone(),
+        Some(Value::Number(value)) => value.to_string(),
+        Some(_) => return Err(eyre!("The value at '{}' is not a scalar", path.join("."))),
+        None => return Ok(None),
+    };
+
+    let lines = content_lines(contents);
+    let span = lines
+        .first()
+        .and_then(|first| find(&lines, 0, first.indent, path))
+        .filter(|span| contents[span.clone()] == expected)
+        .ok_or_else(|| {
+            eyre!(
+                "The value at '{}' must be a plain or quoted scalar in block style to be replaced",
+                path.join(".")
+            )
+        })?;
+
+    Ok(Some(span))
+}
+
+/// Follow `path` through mappings and sequences
+fn lookup<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| match value {
+        Value::Mapping(mapping) => mapping.get(key.as_str()),
+        Value::Sequence(sequence) => sequence.get(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// A line holding content (not blank and not only a comment) of the first document
+struct Line<'a> {
+    text: &'a str,
+    offset: usize,
+    indent: usize,
+}
+
+fn content_lines(contents: &str) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for text in contents.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += text.len();
+
+        let text = text.trim_end_matches(['\n', '\r']);
+        let trimmed = text.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || text.starts_with('%') {
+            continue;
+        }
+
+        // A document start or end marker after the first document's content ends it
+        if text == "---" || text.starts_with("--- ") || text == "..." {
+            match lines.is_empty() {
+                true => continue,
+                false => break,
+            }
+        }
+
+        lines.push(Line {
+            text,
+            offset: line_offset,
+            indent: text.len() - trimmed.len(),
+        });
+    }
+
+    lines
+}
+
+/// Find the span of the scalar at `path` in the node starting at `column` of line `index`
+fn find(lines: &[Line], index: usize, column: usize, path: &[String]) -> Option<Range<usize>> {
+    let line = lines.get(index)?;
+    let rest = line.text.get(column..)?;
+
+    let key = match path.first() {
+        Some(key) => key,
+        None => {
+            return scalar(rest)
+                .map(|span| line.offset + column + span.start..line.offset + column + span.end)
+        }
+    };
+
+    if is_item(rest) {
+        let mut item = index;
+        for _ in 0..key.parse::<usize>().ok()? {
+            item = next_at(lines, item, column, true)?;
+        }
+
+        let (index, column) = value_start(lines, item, column + 1)?;
+        return find(lines, index, column, &path[1..]);
+    }
+
+    let mut entry = index;
+    loop {
+        let text = &lines[entry].text[column..];
+        if let Some((name, colon)) = mapping_key(text) {
+            if name == *key {
+                let (index, column) = value_start(lines, entry, column + colon + 1)?;
+                return find(lines, index, column, &path[1..]);
+            }
+        }
+
+        entry = next_at(lines, entry, column, false)?;
+    }
+}
+
+/// Find the next entry of the collection at `column` after line `index`
+///
+/// Lines indented more than the collection are part of the current entry, and so are sequence
+/// items at the same indentation in a mapping, since a sequence does not have to be indented under
+/// its key. Any other line that is not indented more ends the collection.
+fn next_at(lines: &[Line], index: usize, column: usize, sequence: bool) -> Option<usize> {
+    let next = (index + 1..lines.len()).find(|&i| {
+        lines[i].indent < column
+            || (lines[i].indent == column && (sequence || !is_item(&lines[i].text[column..])))
+    })?;
+    if lines[next].indent < column {
+        return None;
+    }
+
+    let text = &lines[next].text[column..];
+    let is_entry = match sequence {
+        true => is_item(text),
+        false => mapping_key(text).is_some(),
+    };
+    is_entry.then_some(next)
+}
+
+fn is_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Find where the value after a key or a sequence dash at `column` of line `index` starts
+///
+/// The value is either on the same line or, when nothing but a comment follows, a block on the
+/// next lines.
+fn value_start(lines: &[Line], index: usize, column: usize) -> Option<(usize, usize)> {
+    let line = &lines[index];
+    let rest = line.text.get(column..)?;
+    let trimmed = rest.trim_start();
+
+    if !trimmed.is_empty() && !trimmed.starts_with('#') {
+        return Some((index, column + rest.len() - trimmed.len()));
+    }
+
+    let next = lines.get(index + 1)?;
+    Some((index + 1, next.indent))
+}
+
+/// Read a mapping key at the start of `text`, returning the key and the position of its colon
+fn mapping_key(text: &str) -> Option<(String, usize)> {
+    let (name, end) = match text.chars().next()? {
+        '"' | '\'' => {
+            let span = quoted(text)?;
+            (text[span.clone()].to_string(), span.end + 1)
+        }
+        '-' | '#' | '[' | '{' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let colon = text
+                .match_indices(':')
+                .map(|(i, _)| i)
+                .find(|&i| text[i + 1..].is_empty() || text[i + 1..].starts_with([' ', '\t']))?;
+            (text[..colon].trim_end().to_string(), colon)
+        }
+    };
+
+    let colon = end + text[end..].len() - text[end..].trim_start().len();
+    let after = text.get(colon..)?;
+    if !after.starts_with(':') || !(after.len() == 1 || after[1..].starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    Some((name, colon))
+}
+
+/// Find the span of a plain or quoted scalar at the start of `text`, without its quotes
+fn scalar(text: &str) -> Option<Range<usize>> {
+    match text.chars().next()? {
+        '"' | '\'' => quoted(text),
+        '[' | '{' | '&' | '*' | '!' | '|' | '>' | '#' => None,
+        _ => {
+            let end = text.find(" #").unwrap_or(text.len());
+            Some(0..text[..end].trim_end().len())
+        }
+    }
+}
+
+/// Find the span inside the quoted scalar at the start of `text`
+fn quoted(text: &str) -> Option<Range<usize>> {
+    let quote = text.chars().next()?;
+    let mut chars = text.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            c if c == quote => {
+                // A doubled single quote is an escaped quote
+                if quote == '\'' && text[i + 1..].starts_with('\'') {
+                    chars.next();
+                    continue;
+                }
+
+                return Some(1..i);
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
@@ -0,0 +1,302 @@
+use super::{Comment, GitHubOperations, NewRelease};
+use crate::{PullRequest, Release};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use eyre::{eyre, Context, Result};
+use log::{info, warn};
+use regex::Regex;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+const FIELD_SEPARATOR: char = '\u{1f}';
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+/// Read releases and pull requests from a local git checkout instead of the GitHub API
+///
+/// The latest semver tag reachable from `HEAD` is used as the latest release, and pull requests
+/// are recovered from merge commits (`Merge pull request #123 from ...`) and squash-merge commits
+/// (`Title (#123)`). Since git history has no notion of labels, pull requests found this way never
+/// have any labels.
+#[derive(Debug)]
+pub struct GitRepository {
+    path: PathBuf,
+}
+
+struct Commit {
+    committed_at: DateTime<Utc>,
+    subject: String,
+    body: String,
+}
+
+impl GitRepository {
+    pub fn new(path: &Path) -> Self {
+        GitRepository {
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        // The action's container runs as root on a checkout owned by the runner's user, which git
+        // refuses as "dubious ownership" unless the directory is marked as safe
+        let output = Command::new("git")
+            .args(["-c", "safe.directory=*"])
+            .arg("-C")
+            .arg(&self.path)
+            .args(args)
+            .output()
+            .wrap_err("Could not run git, make sure it is installed")?;
+
+        if !output.status.success() {
+            return Err(eyre!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn log(&self, args: &[&str]) -> Result<Vec<Commit>> {
+        let format = format!(
+            "--format=%cI{sep}%s{sep}%b{end}",
+            sep = FIELD_SEPARATOR,
+            end = RECORD_SEPARATOR
+        );
+        let mut log_args = vec!["log", format.as_str()];
+        log_args.extend_from_slice(args);
+        let output = self.git(&log_args)?;
+
+        output
+            .split(RECORD_SEPARATOR)
+            .map(|record| record.trim_start_matches('\n'))
+            .filter(|record| !record.is_empty())
+            .map(|record| {
+                let mut fields = record.splitn(3, FIELD_SEPARATOR);
+                let committed_at = fields.next().unwrap_or_default();
+                let committed_at = DateTime::parse_from_rfc3339(committed_at)
+                    .wrap_err(format!("Unexpected commit date '{}'", committed_at))?
+                    .with_timezone(&Utc);
+
+                Ok(Commit {
+                    committed_at,
+                    subject: fields.next().unwrap_or_default().to_string(),
+                    body: fields.next().unwrap_or_default().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn commits_into_pulls(commits: Vec<Commit>) -> Vec<PullRequest> {
+        let merge_re = Regex::new(r"^Merge pull request #(\d+) from ").unwrap();
+        let squash_re = Regex::new(r"^(.*) \(#(\d+)\)$").unwrap();
+
+        let mut seen = HashSet::new();
+        let mut pulls = Vec::new();
+        for commit in commits {
+            let (number, title, body) = if let Some(captures) = merge_re.captures(&commit.subject) {
+                // Merge commits hold the PR title on the first line of the body
+                let mut lines = commit.body.trim_start().splitn(2, '\n');
+                let title = lines.next().unwrap_or_default().trim().to_string();
+                let body = lines.next().unwrap_or_default().trim().to_string();
+                (captures[1].parse::<u64>(), title, body)
+            } else if let Some(captures) = squash_re.captures(&commit.subject) {
+                let body = commit.body.trim().to_string();
+                (captures[2].parse::<u64>(), captures[1].to_string(), body)
+            } else {
+                continue;
+            };
+
+            let number = match number {
+                Ok(number) => number,
+                Err(_) => continue,
+            };
+            if !seen.insert(number) {
+                continue;
+            }
+
+            info!(
+                "📝 #{} - {} (merged {:?})",
+                number, title, commit.committed_at
+            );
+            let body = if body.is_empty() { None } else { Some(body) };
+            pulls.push(PullRequest::new(
+                number,
+                title,
+                body,
+                Vec::new(),
+                Some(commit.committed_at),
+            ));
+        }
+
+        pulls
+    }
+}
+
+#[async_trait(?Send)]
+impl GitHubOperations for GitRepository {
+    type PullIter = Box<dyn Iterator<Item = PullRequest>>;
+
+    async fn get_pulls<'a, Branch>(
+        &self,
+        bases: Option<impl Iterator<Item = Branch> + 'async_trait>,
+        merged_after: &DateTime<Utc>,
+    ) -> Result<Self::PullIter>
+    where
+        Branch: AsRef<str>,
+    {
+        if bases.is_some() {
+            warn!("Base branches cannot be read from git history, only HEAD is considered");
+        }
+
+        let since = format!("--since={}", merged_after.to_rfc3339());
+        let commits = self
+            .log(&[since.as_str(), "HEAD"])?
+            .into_iter()
+            .filter(|commit| &commit.committed_at > merged_after)
+            .collect();
+
+        Ok(Box::new(Self::commits_into_pulls(commits).into_iter()))
+    }
+
+    async fn get_pulls_in_range(
+        &self,
+        from_tag: Option<&str>,
+        to_commit: &str,
+    ) -> Result<Self::PullIter> {
+        let range = match from_tag {
+            Some(from_tag) => format!("{}..{}", from_tag, to_commit),
+            None => to_commit.to_string(),
+        };
+        let commits = self.log(&[range.as_str()])?;
+
+        Ok(Box::new(Self::commits_into_pulls(commits).into_iter()))
+    }
+
+    async fn get_latest_release(&self, include_prereleases: bool) -> Result<Release> {
+        info!("Reading the latest semver tag in the git repository");
+        let format = format!(
+            "--format=%(refname:short){}%(creatordate:iso-strict)",
+            FIELD_SEPARATOR
+        );
+        let tags = self.git(&["for-each-ref", "--merged", "HEAD", &format, "refs/tags"])?;
+
+        let latest = tags
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(2, FIELD_SEPARATOR);
+                let tag_name = fields.next()?.to_string();
+                let created_at = DateTime::parse_from_rfc3339(fields.next()?).ok()?;
+                let release = Release::new(tag_name, created_at.with_timezone(&Utc));
+                let version = release.get_version().ok()?;
+
+                Some((version, release))
+            })
+            .filter(|(version, _)| include_prereleases || version.pre.is_empty())
+            .max_by(|(a, _), (b, _)| a.cmp(b));
+
+        match latest {
+            Some((_, release)) => {
+                info!("Found latest release (tag: {})", release.tag_name);
+                Ok(release)
+            }
+            None => Ok(Release::default()),
+        }
+    }
+
+    async fn create_tag(&self, _tag_name: &str, _sha: &str) -> Result<()> {
+        // A local tag without its release would be half published, so neither is created
+        Err(eyre!(
+            "Releases cannot be published from git history, use the GitHub backend instead"
+        ))
+    }
+
+    async fn create_release(&self, _release: &NewRelease) -> Result<Release> {
+        Err(eyre!(
+            "Releases cannot be published from git history, use the GitHub backend instead"
+        ))
+    }
+
+    async fn get_comments(&self, _pull_number: u64) -> Result<Vec<Comment>> {
+        Err(eyre!(
+            "Comments cannot be read from git history, use the GitHub backend instead"
+        ))
+    }
+
+    async fn create_comment(&self, _pull_number: u64, _body: &str) -> Result<Comment> {
+        Err(eyre!(
+            "Comments cannot be created from git history, use the GitHub backend instead"
+        ))
+    }
+
+    async fn update_comment(&self, _comment_id: u64, _body: &str) -> Result<Comment> {
+        Err(eyre!(
+            "Comments cannot be updated from git history, use the GitHub backend instead"
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A repository with a merge commit and a squash-merge commit, and no tags
+    fn repository() -> (tempfile::TempDir, GitRepository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = GitRepository::new(dir.path());
+        repository.git(&["init", "-q"]).unwrap();
+        for message in [
+            "Merge pull request #1 from octo/feature\n\nfeat: add a feature",
+            "fix: a fix (#2)",
+        ] {
+            repository
+                .git(&[
+                    "-c",
+                    "user.name=Test",
+                    "-c",
+                    "user.email=test@example.com",
+                    "commit",
+                    "-q",
+                    "--allow-empty",
+                    "-m",
+                    message,
+                ])
+                .unwrap();
+        }
+
+        (dir, repository)
+    }
+
+    #[tokio::test]
+    async fn pulls_in_range_read_every_commit_without_a_tag() {
+        let (_dir, repository) = repository();
+
+        let pulls: Vec<(u64, String)> = repository
+            .get_pulls_in_range(None, "HEAD")
+            .await
+            .unwrap()
+            .map(|pull| (pull.number, pull.title))
+            .collect();
+
+        assert_eq!(
+            pulls,
+            vec![
+                (2, "fix: a fix".to_string()),
+                (1, "feat: add a feature".to_string())
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn publishing_a_release_leaves_no_tag() {
+        let (_dir, repository) = repository();
+        let mut release = NewRelease::new("v1.0.0".to_string(), "v1.0.0".to_string());
+        release.target_commitish = Some("HEAD".to_string());
+
+        assert!(crate::publish_release(&repository, &release).await.is_err());
+        assert_eq!(repository.git(&["tag", "--list"]).unwrap(), "");
+    }
+}
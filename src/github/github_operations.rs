@@ -7,27 +7,56 @@ use semver::Version;
 pub trait GitHubOperations {
     type PullIter: Iterator<Item = PullRequest>;
 
-    async fn get_pulls<'a, Branch, Label>(
+    async fn get_pulls<'a, Branch>(
         &self,
         bases: Option<impl Iterator<Item = Branch> + 'async_trait>,
-        ignore_labels: &[Label],
         merged_after: &DateTime<Utc>,
     ) -> Result<Self::PullIter>
     where
-        Branch: AsRef<str>,
-        Label: AsRef<str>;
-    async fn get_latest_release(&self) -> Result<Release>;
+        Branch: AsRef<str>;
+    /// List the pull requests merged between a tag and a commit, or up to the commit when there is
+    /// no tag (before the first release)
+    async fn get_pulls_in_range(
+        &self,
+        from_tag: Option<&str>,
+        to_commit: &str,
+    ) -> Result<Self::PullIter>;
+    async fn get_latest_release(&self, include_prereleases: bool) -> Result<Release>;
+    async fn create_tag(&self, tag_name: &str, sha: &str) -> Result<()>;
+    async fn create_release(&self, release: &NewRelease) -> Result<Release>;
+    async fn get_comments(&self, pull_number: u64) -> Result<Vec<Comment>>;
+    async fn create_comment(&self, pull_number: u64, body: &str) -> Result<Comment>;
+    async fn update_comment(&self, comment_id: u64, body: &str) -> Result<Comment>;
 }
 
 #[derive(Debug, Clone)]
 pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub body: Option<String>,
     pub labels: Vec<String>,
     pub merged_at: Option<DateTime<Utc>>,
+    pub author: Option<String>,
+    pub url: Option<String>,
 }
 
 impl PullRequest {
-    pub fn new(labels: Vec<String>, merged_at: Option<DateTime<Utc>>) -> Self {
-        PullRequest { labels, merged_at }
+    pub fn new(
+        number: u64,
+        title: String,
+        body: Option<String>,
+        labels: Vec<String>,
+        merged_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        PullRequest {
+            number,
+            title,
+            body,
+            labels,
+            merged_at,
+            author: None,
+            url: None,
+        }
     }
 }
 
@@ -45,6 +74,12 @@ impl Release {
         }
     }
 
+    /// Whether this is the default release, used when the repository has no release yet
+    pub fn is_default(&self) -> bool {
+        let default = Release::default();
+        self.tag_name == default.tag_name && self.created_at == default.created_at
+    }
+
     /// Get the semver version for a GitHub release.
     ///
     /// It is assumed that the tag name associated with the release will be a valid semver version
@@ -54,12 +89,49 @@ impl Release {
         let version = self
             .tag_name
             .strip_prefix('v')
-            .unwrap_or_else(|| self.tag_name.as_str());
+            .unwrap_or(self.tag_name.as_str());
 
         Ok(Version::parse(version)?)
     }
 }
 
+/// A comment on a pull request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub id: u64,
+    pub body: String,
+}
+
+impl Comment {
+    pub fn new(id: u64, body: String) -> Self {
+        Comment { id, body }
+    }
+}
+
+/// A release to publish on GitHub
+#[derive(Debug, Clone)]
+pub struct NewRelease {
+    pub tag_name: String,
+    pub target_commitish: Option<String>,
+    pub name: String,
+    pub body: String,
+    pub draft: bool,
+    pub prerelease: bool,
+}
+
+impl NewRelease {
+    pub fn new(tag_name: String, name: String) -> Self {
+        NewRelease {
+            tag_name,
+            target_commitish: None,
+            name,
+            body: String::new(),
+            draft: false,
+            prerelease: false,
+        }
+    }
+}
+
 impl Default for Release {
     fn default() -> Self {
         Release {
@@ -1,13 +1,24 @@
-use super::GitHubOperations;
+use super::{Comment, GitHubOperations, NewRelease};
 use crate::{PullRequest, Release};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use eyre::{eyre, Result};
+use std::{cell::RefCell, collections::HashMap};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct LocalGitHub {
     pulls: Vec<PullRequest>,
-    releases: Vec<Release>,
+    releases: RefCell<Vec<Release>>,
+    commits: Vec<Commit>,
+    tags: RefCell<HashMap<String, String>>,
+    published: RefCell<Vec<NewRelease>>,
+    comments: RefCell<Vec<(u64, Comment)>>,
+}
+
+#[derive(Debug)]
+struct Commit {
+    sha: String,
+    pulls: Vec<u64>,
 }
 
 impl LocalGitHub {
@@ -20,16 +31,35 @@ impl LocalGitHub {
     }
 
     pub fn add_release(&mut self, release: Release) {
-        self.releases.push(release);
+        self.releases.get_mut().push(release);
     }
-}
 
-impl Default for LocalGitHub {
-    fn default() -> Self {
-        LocalGitHub {
-            pulls: Vec::new(),
-            releases: Vec::new(),
-        }
+    /// Append a commit to the history, associated with the given pull request numbers
+    pub fn add_commit(&mut self, sha: &str, pulls: Vec<u64>) {
+        self.commits.push(Commit {
+            sha: sha.to_string(),
+            pulls,
+        });
+    }
+
+    /// Point a tag at an existing commit
+    pub fn add_tag(&mut self, tag: &str, sha: &str) {
+        self.tags.get_mut().insert(tag.to_string(), sha.to_string());
+    }
+
+    /// All releases that were created through `GitHubOperations::create_release`
+    pub fn published_releases(&self) -> Vec<NewRelease> {
+        self.published.borrow().clone()
+    }
+
+    /// The comments on a pull request, created or updated through `GitHubOperations`
+    pub fn comments(&self, pull_number: u64) -> Vec<Comment> {
+        self.comments
+            .borrow()
+            .iter()
+            .filter(|(number, _)| *number == pull_number)
+            .map(|(_, comment)| comment.clone())
+            .collect()
     }
 }
 
@@ -37,41 +67,116 @@ impl Default for LocalGitHub {
 impl GitHubOperations for LocalGitHub {
     type PullIter = Box<dyn Iterator<Item = PullRequest>>;
 
-    async fn get_pulls<'a, Branch, Label>(
+    async fn get_pulls<'a, Branch>(
         &self,
         _base: Option<impl Iterator<Item = Branch> + 'async_trait>,
-        ignored_labels: &[Label],
         merged_after: &DateTime<Utc>,
     ) -> Result<Self::PullIter>
     where
         Branch: AsRef<str>,
-        Label: AsRef<str>,
     {
         let merged_after = *merged_after; // Copy
-        let ignored_labels: Vec<String> = ignored_labels
+
+        Ok(Box::new(
+            self.pulls
+                .clone()
+                .into_iter()
+                .filter(move |pr| pr.merged_at.unwrap() > merged_after),
+        ))
+    }
+
+    async fn get_pulls_in_range(
+        &self,
+        from_tag: Option<&str>,
+        to_commit: &str,
+    ) -> Result<Self::PullIter> {
+        let tags = self.tags.borrow();
+        // Commits after `from`, or every commit when there is no tag
+        let from = match from_tag {
+            Some(from_tag) => {
+                let from_sha = tags
+                    .get(from_tag)
+                    .ok_or_else(|| eyre!("No tag named '{}'", from_tag))?;
+                let from = self
+                    .commits
+                    .iter()
+                    .position(|c| &c.sha == from_sha)
+                    .ok_or_else(|| eyre!("No commit '{}'", from_sha))?;
+                from + 1
+            }
+            None => 0,
+        };
+        let to = self
+            .commits
             .iter()
-            .map(|l| l.as_ref().to_string())
-            .collect();
+            .position(|c| c.sha == to_commit)
+            .ok_or_else(|| eyre!("No commit '{}'", to_commit))?;
 
+        let numbers: Vec<u64> = self
+            .commits
+            .iter()
+            .take(to + 1)
+            .skip(from)
+            .flat_map(|c| c.pulls.iter().copied())
+            .collect();
         Ok(Box::new(
             self.pulls
                 .clone()
                 .into_iter()
-                .filter(move |pr| pr.merged_at.unwrap() > merged_after)
-                .filter(move |pr| {
-                    pr.labels.iter().all(|label| {
-                        !ignored_labels
-                            .iter()
-                            .any(|ignored_label| label == ignored_label)
-                    })
-                }),
+                .filter(move |pr| numbers.contains(&pr.number)),
         ))
     }
 
-    async fn get_latest_release(&self) -> Result<Release> {
+    async fn get_latest_release(&self, include_prereleases: bool) -> Result<Release> {
         self.releases
-            .last()
+            .borrow()
+            .iter()
+            .rev()
+            .find(|release| {
+                include_prereleases || matches!(release.get_version(), Ok(v) if v.pre.is_empty())
+            })
             .cloned()
             .ok_or_else(|| eyre!("No releases"))
     }
+
+    async fn create_tag(&self, tag_name: &str, sha: &str) -> Result<()> {
+        let mut tags = self.tags.borrow_mut();
+        if tags.contains_key(tag_name) {
+            return Err(eyre!("Tag '{}' already exists", tag_name));
+        }
+
+        tags.insert(tag_name.to_string(), sha.to_string());
+        Ok(())
+    }
+
+    async fn create_release(&self, release: &NewRelease) -> Result<Release> {
+        let created = Release::new(release.tag_name.clone(), Utc::now());
+        self.releases.borrow_mut().push(created.clone());
+        self.published.borrow_mut().push(release.clone());
+
+        Ok(created)
+    }
+
+    async fn get_comments(&self, pull_number: u64) -> Result<Vec<Comment>> {
+        Ok(self.comments(pull_number))
+    }
+
+    async fn create_comment(&self, pull_number: u64, body: &str) -> Result<Comment> {
+        let mut comments = self.comments.borrow_mut();
+        let comment = Comment::new(comments.len() as u64 + 1, body.to_string());
+        comments.push((pull_number, comment.clone()));
+
+        Ok(comment)
+    }
+
+    async fn update_comment(&self, comment_id: u64, body: &str) -> Result<Comment> {
+        let mut comments = self.comments.borrow_mut();
+        let (_, comment) = comments
+            .iter_mut()
+            .find(|(_, comment)| comment.id == comment_id)
+            .ok_or_else(|| eyre!("No comment {}", comment_id))?;
+        comment.body = body.to_string();
+
+        Ok(comment.clone())
+    }
 }
@@ -1,8 +1,10 @@
-pub use github_operations::{GitHubOperations, PullRequest, Release};
+pub use git_repository::GitRepository;
+pub use github_operations::{Comment, GitHubOperations, NewRelease, PullRequest, Release};
 pub use local_github::LocalGitHub;
 pub use real_github::GitHub;
 
 mod error;
+mod git_repository;
 mod github_operations;
 mod local_github;
 mod real_github;
@@ -1,11 +1,15 @@
-use super::{error::GitHubError, GitHubOperations};
+use super::{error::GitHubError, Comment, GitHubOperations, NewRelease};
 use crate::{PullRequest, Release};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use eyre::{eyre, Context, Result};
 use log::info;
-use octocrab::{params::State, Octocrab};
-use std::sync::Arc;
+use octocrab::{
+    params::{pulls::Sort, repos::Reference, Direction, State},
+    Octocrab,
+};
+use serde::Deserialize;
+use std::{collections::HashSet, sync::Arc};
 
 #[derive(Debug)]
 pub struct GitHub {
@@ -31,79 +35,274 @@ impl GitHub {
     }
 }
 
+#[derive(Deserialize)]
+struct Comparison {
+    total_commits: usize,
+    commits: Vec<ComparedCommit>,
+}
+
+#[derive(Deserialize)]
+struct ComparedCommit {
+    sha: String,
+}
+
+impl GitHub {
+    /// List the SHA of every commit reachable from `head` but not from `base`
+    async fn get_commits_between(&self, base: &str, head: &str) -> Result<Vec<String>> {
+        let route = format!(
+            "repos/{}/{}/compare/{}...{}",
+            self.owner, self.repo, base, head
+        );
+
+        let mut commits = Vec::new();
+        let mut page: u32 = 1;
+        loop {
+            let comparison: Comparison = self
+                .octocrab
+                .get(&route, Some(&[("per_page", 100), ("page", page)]))
+                .await?;
+
+            let received = comparison.commits.len();
+            commits.extend(comparison.commits.into_iter().map(|c| c.sha));
+
+            if received == 0 || commits.len() >= comparison.total_commits {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(commits)
+    }
+
+    /// List the SHA of every commit reachable from `head`, for when there is no release to compare
+    /// with
+    async fn get_commits_up_to(&self, head: &str) -> Result<Vec<String>> {
+        let route = format!("repos/{}/{}/commits", self.owner, self.repo);
+
+        let mut commits = Vec::new();
+        let mut page: u32 = 1;
+        loop {
+            let listed: Vec<ComparedCommit> = self
+                .octocrab
+                .get(
+                    &route,
+                    Some(&[
+                        ("sha", head.to_string()),
+                        ("per_page", "100".to_string()),
+                        ("page", page.to_string()),
+                    ]),
+                )
+                .await?;
+
+            if listed.is_empty() {
+                break;
+            }
+            commits.extend(listed.into_iter().map(|c| c.sha));
+            page += 1;
+        }
+
+        Ok(commits)
+    }
+
+    /// Find the release with the highest version, including prereleases
+    ///
+    /// GitHub's "latest release" never includes prereleases, so the most recent releases are
+    /// listed instead. Draft releases and releases without a semver tag are skipped.
+    async fn get_latest_prerelease(&self) -> Result<Release> {
+        info!("Querying GitHub for the latest release, including prereleases");
+        let releases = self
+            .octocrab
+            .repos(&self.owner, &self.repo)
+            .releases()
+            .list()
+            .per_page(100)
+            .send()
+            .await?;
+
+        let latest = releases
+            .into_iter()
+            .filter(|rel| !rel.draft)
+            .filter_map(|rel| {
+                let release = Release::new(rel.tag_name, rel.created_at?);
+                let version = release.get_version().ok()?;
+
+                Some((version, release))
+            })
+            .max_by(|(a, _), (b, _)| a.cmp(b));
+
+        match latest {
+            Some((_, release)) => {
+                info!("Found latest release (tag: {})", release.tag_name);
+                Ok(release)
+            }
+            None => Ok(Release::default()),
+        }
+    }
+}
+
+fn simplify(pr: octocrab::models::pulls::PullRequest) -> PullRequest {
+    let labels = pr
+        .labels
+        .unwrap_or_default()
+        .iter()
+        .map(|l| l.name.clone())
+        .collect();
+
+    let mut simplified = PullRequest::new(pr.number, pr.title, pr.body, labels, pr.merged_at);
+    simplified.author = Some(pr.user.login);
+    simplified.url = Some(pr.html_url.to_string());
+
+    simplified
+}
+
 #[async_trait(?Send)]
 impl GitHubOperations for GitHub {
     type PullIter = Box<dyn Iterator<Item = PullRequest>>;
 
-    async fn get_pulls<'a, Branch, Label>(
+    async fn get_pulls<'a, Branch>(
         &self,
-        mut bases: Option<impl Iterator<Item = Branch> + 'async_trait>,
-        ignored_labels: &[Label],
+        bases: Option<impl Iterator<Item = Branch> + 'async_trait>,
         merged_after: &DateTime<Utc>,
     ) -> Result<Self::PullIter>
     where
         Branch: AsRef<str>,
-        Label: AsRef<str>,
     {
-        let pulls = self
+        let bases: Option<Vec<String>> =
+            bases.map(|bases| bases.map(|base| base.as_ref().to_string()).collect());
+
+        let mut page = self
             .octocrab
             .pulls(&self.owner, &self.repo)
             .list()
             .state(State::Closed)
+            .sort(Sort::Updated)
+            .direction(Direction::Descending)
             .per_page(100)
             .send()
             .await?;
 
-        let eligible = pulls
-            .into_iter()
-            .filter(|pr| {
-                let ignored = match &pr.labels {
-                    Some(pr_labels) => pr_labels.iter().all(|label| {
-                        ignored_labels
-                            .iter()
-                            .any(|ignored_label| label.name == ignored_label.as_ref())
-                    }),
-                    None => true, // No labels on the PR means we can ignore it
-                };
+        let mut inspected = 0;
+        let mut eligible = Vec::new();
+        loop {
+            let mut reached_release = false;
 
-                if ignored {
-                    info!("🗑️  #{} - {} (ignored)", pr.number, pr.title);
-                } else {
-                    info!(
-                        "📝 #{} - {} (merged {:?})",
-                        pr.number, pr.title, pr.merged_at
-                    );
+            for pr in page.take_items() {
+                // PRs are sorted by update time, and a PR is always updated when it gets merged,
+                // so once we see a PR updated before the release, all remaining PRs are older.
+                if matches!(pr.updated_at, Some(updated) if &updated < merged_after) {
+                    reached_release = true;
+                    break;
                 }
+                inspected += 1;
 
-                !ignored
-            })
-            .filter(move |pr| pr.merged_at.is_some() && &pr.merged_at.unwrap() > merged_after)
-            .filter(|pr| {
-                let pr_base = pr.base.label.split(':').last().unwrap_or_else(|| {
+                let merged = match pr.merged_at {
+                    Some(merged_at) => &merged_at > merged_after,
+                    None => false,
+                };
+                if !merged {
+                    continue;
+                }
+
+                let pr_base = pr.base.label.split(':').next_back().unwrap_or_else(|| {
                     panic!("Unexpected format for PR base: '{}'", pr.base.label)
                 });
+                let on_base = match &bases {
+                    Some(bases) => bases.iter().any(|base| base == pr_base),
+                    None => true,
+                };
+                if !on_base {
+                    continue;
+                }
 
-                bases.is_none() || bases.as_mut().unwrap().any(|base| base.as_ref() == pr_base)
-            });
+                info!(
+                    "📝 #{} - {} (merged {:?})",
+                    pr.number, pr.title, pr.merged_at
+                );
+                eligible.push(pr);
+            }
 
-        let simplified: Vec<PullRequest> = eligible
-            .into_iter()
-            .map(|p| {
-                let labels = p
-                    .labels
-                    .unwrap_or_default()
-                    .iter()
-                    .map(|l| l.name.clone())
-                    .collect();
-
-                PullRequest::new(labels, p.merged_at)
-            })
-            .collect(); // We collect to avoid cloning `bases` for every PR
+            if reached_release {
+                break;
+            }
+
+            page = match self.octocrab.get_page(&page.next).await? {
+                Some(next_page) => next_page,
+                None => break,
+            };
+        }
+
+        info!(
+            "Inspected {} closed pull requests, {} were merged after the release",
+            inspected,
+            eligible.len()
+        );
+
+        let simplified = eligible.into_iter().map(simplify).collect::<Vec<_>>();
+
+        Ok(Box::new(simplified.into_iter()))
+    }
+
+    async fn get_pulls_in_range(
+        &self,
+        from_tag: Option<&str>,
+        to_commit: &str,
+    ) -> Result<Self::PullIter> {
+        let commits = match from_tag {
+            Some(from_tag) => {
+                info!("Comparing '{}' with '{}'", from_tag, to_commit);
+                let commits = self
+                    .get_commits_between(from_tag, to_commit)
+                    .await
+                    .wrap_err(format!(
+                        "Could not compare '{}' with '{}', make sure the release tag exists",
+                        from_tag, to_commit
+                    ))?;
+                info!("Found {} commits since '{}'", commits.len(), from_tag);
+                commits
+            }
+            None => {
+                info!("No release yet, listing every commit up to '{}'", to_commit);
+                let commits = self
+                    .get_commits_up_to(to_commit)
+                    .await
+                    .wrap_err(format!("Could not list the commits of '{}'", to_commit))?;
+                info!("Found {} commits", commits.len());
+                commits
+            }
+        };
+
+        let mut seen = HashSet::new();
+        let mut eligible = Vec::new();
+        for sha in commits {
+            let route = format!("repos/{}/{}/commits/{}/pulls", self.owner, self.repo, sha);
+            let pulls: Vec<octocrab::models::pulls::PullRequest> =
+                self.octocrab.get(route, None::<&()>).await?;
+
+            for pr in pulls {
+                if pr.merged_at.is_none() || !seen.insert(pr.number) {
+                    continue;
+                }
+
+                info!(
+                    "📝 #{} - {} (merged {:?})",
+                    pr.number, pr.title, pr.merged_at
+                );
+                eligible.push(pr);
+            }
+        }
+
+        info!("Found {} pull requests in the commit range", eligible.len());
+
+        let simplified = eligible.into_iter().map(simplify).collect::<Vec<_>>();
 
         Ok(Box::new(simplified.into_iter()))
     }
 
-    async fn get_latest_release(&self) -> Result<Release> {
+    async fn get_latest_release(&self, include_prereleases: bool) -> Result<Release> {
+        if include_prereleases {
+            return self.get_latest_prerelease().await;
+        }
+
         info!("Querying GitHub for the latest release");
         let latest_release = self
             .octocrab
@@ -129,4 +328,197 @@ impl GitHubOperations for GitHub {
 
         Ok(simplified)
     }
+
+    async fn create_tag(&self, tag_name: &str, sha: &str) -> Result<()> {
+        info!("Creating tag '{}' on '{}'", tag_name, sha);
+        self.octocrab
+            .repos(&self.owner, &self.repo)
+            .create_ref(&Reference::Tag(tag_name.to_string()), sha)
+            .await
+            .wrap_err(format!("Could not create tag '{}'", tag_name))?;
+
+        Ok(())
+    }
+
+    async fn create_release(&self, release: &NewRelease) -> Result<Release> {
+        info!("Creating release '{}'", release.name);
+        let handler = self.octocrab.repos(&self.owner, &self.repo);
+        let releases = handler.releases();
+        let mut builder = releases
+            .create(&release.tag_name)
+            .name(&release.name)
+            .body(&release.body)
+            .draft(release.draft)
+            .prerelease(release.prerelease);
+        if let Some(target) = &release.target_commitish {
+            builder = builder.target_commitish(target);
+        }
+
+        let created = builder
+            .send()
+            .await
+            .wrap_err(format!("Could not create release '{}'", release.name))?;
+        info!("Created release {}", created.html_url);
+
+        Ok(Release::new(
+            created.tag_name,
+            created.created_at.unwrap_or_else(Utc::now),
+        ))
+    }
+
+    async fn get_comments(&self, pull_number: u64) -> Result<Vec<Comment>> {
+        let mut page = self
+            .octocrab
+            .issues(&self.owner, &self.repo)
+            .list_comments(pull_number)
+            .per_page(100)
+            .send()
+            .await
+            .wrap_err(format!("Could not list the comments on #{}", pull_number))?;
+
+        let mut comments = Vec::new();
+        loop {
+            comments.extend(
+                page.items
+                    .into_iter()
+                    .map(|comment| Comment::new(*comment.id, comment.body.unwrap_or_default())),
+            );
+
+            page = match self.octocrab.get_page(&page.next).await? {
+                Some(next_page) => next_page,
+                None => break,
+            };
+        }
+
+        Ok(comments)
+    }
+
+    async fn create_comment(&self, pull_number: u64, body: &str) -> Result<Comment> {
+        info!("Commenting on #{}", pull_number);
+        let created = self
+            .octocrab
+            .issues(&self.owner, &self.repo)
+            .create_comment(pull_number, body)
+            .await
+            .wrap_err(format!("Could not comment on #{}", pull_number))?;
+        info!("Created comment {}", created.html_url);
+
+        Ok(Comment::new(*created.id, created.body.unwrap_or_default()))
+    }
+
+    async fn update_comment(&self, comment_id: u64, body: &str) -> Result<Comment> {
+        info!("Updating comment {}", comment_id);
+        let route = format!(
+            "repos/{}/{}/issues/comments/{}",
+            self.owner, self.repo, comment_id
+        );
+        let updated: octocrab::models::issues::Comment = self
+            .octocrab
+            .patch(route, Some(&serde_json::json!({ "body": body })))
+            .await
+            .wrap_err(format!("Could not update comment {}", comment_id))?;
+        info!("Updated comment {}", updated.html_url);
+
+        Ok(Comment::new(*updated.id, updated.body.unwrap_or_default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IgnoreMode, LocalGitHub, PullFilter};
+    use serde_json::{json, Value};
+
+    fn user() -> Value {
+        let url = "https://api.github.com/users/octocat";
+        json!({
+            "login": "octocat", "id": 1, "node_id": "U_1", "avatar_url": url, "gravatar_id": "",
+            "url": url, "html_url": url, "followers_url": url, "following_url": url,
+            "gists_url": url, "starred_url": url, "subscriptions_url": url,
+            "organizations_url": url, "repos_url": url, "events_url": url,
+            "received_events_url": url, "type": "User", "site_admin": false
+        })
+    }
+
+    /// A closed pull request like the GitHub API returns it
+    fn api_pull(number: u64, labels: &[&str]) -> octocrab::models::pulls::PullRequest {
+        let url = format!("https://github.com/octo/repo/pull/{}", number);
+        let labels: Vec<Value> = labels
+            .iter()
+            .enumerate()
+            .map(|(id, name)| {
+                json!({
+                    "id": id, "node_id": "L", "url": url, "name": name,
+                    "color": "ffffff", "default": false
+                })
+            })
+            .collect();
+
+        serde_json::from_value(json!({
+            "url": url, "id": number, "node_id": "PR", "html_url": url, "diff_url": url,
+            "patch_url": url, "issue_url": url, "commits_url": url, "review_comments_url": url,
+            "review_comment_url": url, "comments_url": url, "statuses_url": url,
+            "number": number, "state": "closed", "title": "Title", "user": user(),
+            "labels": labels, "created_at": "2021-01-01T00:00:00Z",
+            "merged_at": "2021-01-02T00:00:00Z", "assignees": [], "requested_reviewers": [],
+            "requested_teams": [], "rebaseable": null, "draft": false,
+            "head": { "ref": "feature", "sha": "abc" },
+            "base": { "label": "octo:main", "ref": "main", "sha": "def", "user": user() }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn backends_feed_the_same_pulls_to_the_filter() {
+        let labels: [&[&str]; 5] = [
+            &[],
+            &["skip"],
+            &["skip", "chore"],
+            &["skip", "feat"],
+            &["feat"],
+        ];
+        let merged_at: DateTime<Utc> = "2021-01-02T00:00:00Z".parse().unwrap();
+        let mut local = LocalGitHub::new();
+        for (number, labels) in labels.iter().enumerate() {
+            let labels = labels.iter().map(|label| label.to_string()).collect();
+            local.add_pull(PullRequest::new(
+                number as u64,
+                "Title".to_string(),
+                None,
+                labels,
+                Some(merged_at),
+            ));
+        }
+        let api_pulls: Vec<PullRequest> = labels
+            .iter()
+            .enumerate()
+            .map(|(number, labels)| simplify(api_pull(number as u64, labels)))
+            .collect();
+        let local_pulls: Vec<PullRequest> = local
+            .get_pulls(
+                None::<std::iter::Empty<&str>>,
+                &"2021-01-01T00:00:00Z".parse().unwrap(),
+            )
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(local_pulls.len(), api_pulls.len());
+
+        for mode in [IgnoreMode::Any, IgnoreMode::All] {
+            for include_unlabeled in [true, false] {
+                let filter = PullFilter::new(
+                    vec!["skip".to_string(), "chore".to_string()],
+                    mode,
+                    include_unlabeled,
+                );
+                for (api_pull, local_pull) in api_pulls.iter().zip(&local_pulls) {
+                    assert_eq!(api_pull.labels, local_pull.labels);
+                    assert_eq!(
+                        filter.ignore_reason(api_pull),
+                        filter.ignore_reason(local_pull)
+                    );
+                }
+            }
+        }
+    }
 }
@@ -1,26 +1,44 @@
 use std::path::Path;
 
-pub use bump_version::BumpRules;
-use bump_version::{bump_in_file, bump_version};
+pub use bump_decision::{BumpDecision, BumpLevel, Contribution, PullDecision};
+use bump_version::{bump_field_in_file, bump_in_file, explain_bump};
+pub use bump_version::{BumpRules, Occurrences};
+pub use changelog::Changelog;
 use chrono::{DateTime, Utc};
 use eyre::{Context, Result};
-use github::GitHubOperations;
-pub use github::{GitHub, LocalGitHub, PullRequest, Release};
+pub use file_change::{FileChange, FileChanges};
+pub use file_format::FileFormat;
+pub use github::{
+    Comment, GitHub, GitHubOperations, GitRepository, LocalGitHub, NewRelease, PullRequest, Release,
+};
+pub use pull_filter::{IgnoreMode, PullFilter};
 use semver::Version;
+pub use version_template::VersionTemplate;
 
+mod bump_decision;
 mod bump_version;
+mod cargo_workspace;
+mod changelog;
+mod file_change;
+mod file_format;
 mod github;
+mod pull_filter;
+mod version_template;
 
 /// Fetch the latest release from GitHub
 ///
 /// If no releases are found, the default is to return a release with the version 0.1.0,
-/// with a creation date far into the past.
-pub async fn get_latest_release(github: &GitHub) -> Result<Release>
+/// with a creation date far into the past. Prereleases are only considered when
+/// `include_prereleases` is set.
+pub async fn get_latest_release<GitHub>(
+    github: &GitHub,
+    include_prereleases: bool,
+) -> Result<Release>
 where
     GitHub: GitHubOperations,
 {
     github
-        .get_latest_release()
+        .get_latest_release(include_prereleases)
         .await
         .wrap_err("Could not find latest release in GitHub")
 }
@@ -31,12 +49,13 @@ where
 ///
 /// * `github` - Any type implementing the `GitHubOperations` trait
 /// * `bases` - Only get pull requests that were merged into those bases (`None` means get all PRs)
-/// * `ignored_labels` - Do not get pull requests that have one of those labels on it
 /// * `merged_after` - Only get pull requests that have been merged after this date
+///
+/// Ignored pull requests are included, the `PullFilter` in the bump rules decides which are
+/// ignored.
 pub async fn get_pulls<GitHub, Branch, PRs>(
     github: &GitHub,
     bases: Option<&Vec<Branch>>,
-    ignored_labels: Vec<String>,
     merged_after: &DateTime<Utc>,
 ) -> Result<PRs>
 where
@@ -45,15 +64,97 @@ where
     PRs: Iterator<Item = PullRequest>,
 {
     github
-        .get_pulls(
-            bases.map(|bases| bases.clone().into_iter()),
-            &ignored_labels,
-            merged_after,
-        )
+        .get_pulls(bases.map(|bases| bases.clone().into_iter()), merged_after)
         .await
         .wrap_err("Could not list pull requests in GitHub")
 }
 
+/// Fetch the pull requests merged between a release and a commit
+///
+/// This compares the release's tag with `head` and finds the pull requests associated with every
+/// commit in that range, so the result is exactly what ships in the next version.
+///
+/// # Arguments
+///
+/// * `github` - Any type implementing the `GitHubOperations` trait
+/// * `release` - The release to start from (its tag must exist in the repository)
+/// * `head` - The commit to stop at, usually the one that triggered the workflow
+///
+/// Before the first release (when `release` is the default one), every pull request in the history
+/// of `head` is included. Like with `get_pulls`, ignored pull requests are included.
+pub async fn get_pulls_in_range<GitHub, PRs>(
+    github: &GitHub,
+    release: &Release,
+    head: &str,
+) -> Result<PRs>
+where
+    GitHub: GitHubOperations<PullIter = PRs>,
+    PRs: Iterator<Item = PullRequest>,
+{
+    github
+        .get_pulls_in_range(
+            (!release.is_default()).then_some(release.tag_name.as_str()),
+            head,
+        )
+        .await
+        .wrap_err("Could not list pull requests in the commit range")
+}
+
+/// Publish a new release on GitHub
+///
+/// When the release has a target commit, its tag is created on that commit first. Otherwise,
+/// GitHub creates the tag on the repository's default branch.
+pub async fn publish_release<GitHub>(github: &GitHub, release: &NewRelease) -> Result<Release>
+where
+    GitHub: GitHubOperations,
+{
+    if let Some(sha) = &release.target_commitish {
+        github
+            .create_tag(&release.tag_name, sha)
+            .await
+            .wrap_err("Could not create the release tag")?;
+    }
+
+    github
+        .create_release(release)
+        .await
+        .wrap_err("Could not publish the release")
+}
+
+/// Create or update the single comment on a pull request that is identified by a marker
+///
+/// The marker (usually an HTML comment like `<!-- pr-bump -->`) is added at the start of the
+/// comment, so the next call finds the comment and updates it instead of adding another one.
+pub async fn publish_sticky_comment<GitHub>(
+    github: &GitHub,
+    pull_number: u64,
+    marker: &str,
+    body: &str,
+) -> Result<Comment>
+where
+    GitHub: GitHubOperations,
+{
+    let body = format!("{}\n{}", marker, body);
+    let comments = github
+        .get_comments(pull_number)
+        .await
+        .wrap_err("Could not read the pull request's comments")?;
+
+    match comments
+        .iter()
+        .find(|comment| comment.body.contains(marker))
+    {
+        Some(comment) => github
+            .update_comment(comment.id, &body)
+            .await
+            .wrap_err("Could not update the pull request's comment"),
+        None => github
+            .create_comment(pull_number, &body)
+            .await
+            .wrap_err("Could not comment on the pull request"),
+    }
+}
+
 /// Calculate the next version for a project
 ///
 /// Based on the current version, some rules for bumping versions, and pull requests, find the next
@@ -63,19 +164,308 @@ pub fn get_next_version(
     bump_rules: &BumpRules,
     pulls: impl Iterator<Item = PullRequest>,
 ) -> Version {
-    bump_version(current_version, bump_rules, pulls)
+    explain_bump(current_version, bump_rules, pulls).next_version
+}
+
+/// Calculate the next version for a project, and explain why it was chosen
+///
+/// The decision lists what every pull request contributed (the labels and titles that matched,
+/// with their bump level), the pull requests that did not ask for a bump, and the rules that
+/// changed the outcome.
+pub fn explain_next_version(
+    current_version: &Version,
+    bump_rules: &BumpRules,
+    pulls: impl Iterator<Item = PullRequest>,
+) -> BumpDecision {
+    explain_bump(current_version, bump_rules, pulls)
 }
 
-// Bump the version in a given file
-//
-// The prefix is what comes immediately before the version number and is not a regex.
-// For example, to bump `Cargo.toml`, `prefix` could be `version = \"`. This is just
-// to make sure only the correct thing is bumped and not another random version number.
+/// Bump the version in a given file and write it
+///
+/// This is [`stage_file_update`] for a single file, see it for the meaning of the arguments.
 pub fn update_file(
     current_version: &Version,
     next_version: &Version,
     version_prefix: &str,
+    template: &VersionTemplate,
+    expected: Occurrences,
     file_path: &Path,
 ) -> Result<()> {
-    bump_in_file(current_version, next_version, version_prefix, file_path)
+    let mut changes = FileChanges::new();
+    stage_file_update(
+        current_version,
+        next_version,
+        version_prefix,
+        template,
+        expected,
+        file_path,
+        &mut changes,
+    )?;
+    changes.write()
+}
+
+/// Bump the version in a given file, adding the change to a set of changes without writing it
+///
+/// The prefix is what comes immediately before the version number and is not a regex.
+/// For example, to bump `Cargo.toml`, `prefix` could be `version = \"`. This is just
+/// to make sure only the correct thing is bumped and not another random version number.
+/// The file must contain the expected occurrences of the current version, or nothing is changed.
+/// The template describes how the version is written, like `v{version}`.
+///
+/// Returns how many occurrences of the current version were bumped.
+pub fn stage_file_update(
+    current_version: &Version,
+    next_version: &Version,
+    version_prefix: &str,
+    template: &VersionTemplate,
+    expected: Occurrences,
+    file_path: &Path,
+    changes: &mut FileChanges,
+) -> Result<usize> {
+    let (change, count) = bump_in_file(
+        current_version,
+        next_version,
+        version_prefix,
+        template,
+        expected,
+        file_path,
+        changes,
+    )?;
+    changes.add(change);
+
+    Ok(count)
+}
+
+/// Bump the version in a single field of a structured file and write it
+///
+/// This is [`stage_file_field_update`] for a single file, see it for the meaning of the arguments.
+pub fn update_file_field(
+    current_version: &Version,
+    next_version: &Version,
+    format: FileFormat,
+    key_path: &str,
+    template: &VersionTemplate,
+    file_path: &Path,
+) -> Result<()> {
+    let mut changes = FileChanges::new();
+    stage_file_field_update(
+        current_version,
+        next_version,
+        format,
+        key_path,
+        template,
+        file_path,
+        &mut changes,
+    )?;
+    changes.write()
+}
+
+/// Bump the version in a single field of a structured file, adding the change to a set of changes
+///
+/// The key path points to the field holding the version, like `package.version` in `Cargo.toml`
+/// or `$.version` in `package.json`. Only that field is changed, the rest of the file (including
+/// comments and formatting) is left as is. The field holds the version written with the template.
+pub fn stage_file_field_update(
+    current_version: &Version,
+    next_version: &Version,
+    format: FileFormat,
+    key_path: &str,
+    template: &VersionTemplate,
+    file_path: &Path,
+    changes: &mut FileChanges,
+) -> Result<()> {
+    let change = bump_field_in_file(
+        current_version,
+        next_version,
+        format,
+        key_path,
+        template,
+        file_path,
+        changes,
+    )?;
+    changes.add(change);
+
+    Ok(())
+}
+
+/// Bump the crates of a Cargo workspace and write every changed manifest and `Cargo.lock`
+///
+/// This is [`stage_cargo_workspace_update`] on its own, the files are written together.
+pub fn update_cargo_workspace(
+    current_version: &Version,
+    next_version: &Version,
+    manifest_path: &Path,
+) -> Result<()> {
+    let mut changes = FileChanges::new();
+    stage_cargo_workspace_update(current_version, next_version, manifest_path, &mut changes)?;
+    changes.write()
+}
+
+/// Bump the crates of a Cargo workspace, the requirements on them and `Cargo.lock`
+///
+/// Members are discovered from the root manifest, and only crates that are at the current version
+/// are bumped. Cargo itself is not invoked, so this works without a Rust toolchain. The changes
+/// are added to the set of changes, without writing them.
+pub fn stage_cargo_workspace_update(
+    current_version: &Version,
+    next_version: &Version,
+    manifest_path: &Path,
+    changes: &mut FileChanges,
+) -> Result<()> {
+    let workspace_changes =
+        cargo_workspace::bump_workspace(current_version, next_version, manifest_path, changes)
+            .wrap_err("Could not bump the Cargo workspace")?;
+    for change in workspace_changes {
+        changes.add(change);
+    }
+
+    Ok(())
+}
+
+/// Add a changelog entry for the next version at the top of a changelog file and write it
+///
+/// This is [`stage_changelog_update`] on its own.
+pub fn update_changelog(tag_name: &str, changelog: &str, file_path: &Path) -> Result<()> {
+    let mut changes = FileChanges::new();
+    stage_changelog_update(tag_name, changelog, file_path, &mut changes)?;
+    changes.write()
+}
+
+/// Add a changelog entry for the next version at the top of a changelog file
+///
+/// The entry has a heading with the tag name of the next version and the date, followed by the
+/// rendered changelog. Like the other `stage_*` functions, the change is only written with the
+/// rest of the set of changes.
+pub fn stage_changelog_update(
+    tag_name: &str,
+    changelog: &str,
+    file_path: &Path,
+    changes: &mut FileChanges,
+) -> Result<()> {
+    let change = changelog::prepend_to_file(tag_name, &Utc::now(), changelog, file_path, changes)?;
+    changes.add(change);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn publish_release_tags_the_target_and_records_the_release() {
+        let mut github = LocalGitHub::new();
+        github.add_commit("abc123", vec![]);
+        let mut release = NewRelease::new("v1.2.0".to_string(), "v1.2.0".to_string());
+        release.target_commitish = Some("abc123".to_string());
+
+        let published = publish_release(&github, &release).await.unwrap();
+
+        assert_eq!(published.tag_name, "v1.2.0");
+        let releases = github.published_releases();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].tag_name, "v1.2.0");
+        assert_eq!(releases[0].target_commitish.as_deref(), Some("abc123"));
+        assert_eq!(
+            get_latest_release(&github, false).await.unwrap().tag_name,
+            "v1.2.0"
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_release_fails_when_the_tag_exists() {
+        let mut github = LocalGitHub::new();
+        github.add_commit("abc123", vec![]);
+        github.add_tag("v1.2.0", "abc123");
+        let mut release = NewRelease::new("v1.2.0".to_string(), "v1.2.0".to_string());
+        release.target_commitish = Some("abc123".to_string());
+
+        assert!(publish_release(&github, &release).await.is_err());
+        assert!(github.published_releases().is_empty());
+    }
+
+    fn pull(number: u64) -> PullRequest {
+        PullRequest::new(number, format!("Pull {}", number), None, Vec::new(), None)
+    }
+
+    #[tokio::test]
+    async fn pulls_in_range_start_after_the_release_tag() {
+        let mut github = LocalGitHub::new();
+        github.add_pull(pull(1));
+        github.add_pull(pull(2));
+        github.add_commit("a", vec![1]);
+        github.add_commit("b", vec![2]);
+        github.add_tag("v1.0.0", "a");
+        let release = Release::new("v1.0.0".to_string(), Utc::now());
+
+        let pulls: Vec<u64> = get_pulls_in_range(&github, &release, "b")
+            .await
+            .unwrap()
+            .map(|pull| pull.number)
+            .collect();
+
+        assert_eq!(pulls, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn pulls_in_range_include_every_commit_before_the_first_release() {
+        let mut github = LocalGitHub::new();
+        github.add_pull(pull(1));
+        github.add_pull(pull(2));
+        github.add_commit("a", vec![1]);
+        github.add_commit("b", vec![2]);
+
+        let pulls: Vec<u64> = get_pulls_in_range(&github, &Release::default(), "b")
+            .await
+            .unwrap()
+            .map(|pull| pull.number)
+            .collect();
+
+        assert_eq!(pulls, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn sticky_comment_is_created_then_updated() {
+        let github = LocalGitHub::new();
+        github.create_comment(12, "Looks good").await.unwrap();
+
+        let created = publish_sticky_comment(&github, 12, "<!-- pr-bump -->", "1.1.0")
+            .await
+            .unwrap();
+        assert_eq!(github.comments(12).len(), 2);
+        assert_eq!(created.body, "<!-- pr-bump -->\n1.1.0");
+
+        let updated = publish_sticky_comment(&github, 12, "<!-- pr-bump -->", "2.0.0")
+            .await
+            .unwrap();
+
+        assert_eq!(created.id, updated.id);
+        let comments = github.comments(12);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].body, "Looks good");
+        assert_eq!(comments[1].body, "<!-- pr-bump -->\n2.0.0");
+        assert!(github.comments(13).is_empty());
+    }
+
+    #[test]
+    fn update_file_writes_the_bumped_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.toml");
+        std::fs::write(&path, "version = \"1.0.0\"\n").unwrap();
+
+        update_file(
+            &Version::new(1, 0, 0),
+            &Version::new(1, 1, 0),
+            "version = \"",
+            &VersionTemplate::default(),
+            Occurrences::default(),
+            &path,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "version = \"1.1.0\"\n"
+        );
+    }
 }
@@ -1,15 +1,35 @@
-use actions_tools::{close_group, group_lines};
-use config::{actions_config::ActionConfig, pr_bump_config::PrBumpConfig};
-use eyre::Result;
-use log::{error, info, LevelFilter};
-use pr_bump_lib::{get_latest_release, get_next_version, get_pulls, update_file, GitHub};
+use actions_tools::{close_group, escape_command_value, group_lines};
+use cli::{CliArgs, Command, USAGE};
+use config::{
+    actions_config::{fail_on_error_from_env, ActionConfig, Backend, Mode},
+    event::PullRequestEvent,
+    pr_bump_config::{PrBumpConfig, PullSelection},
+    schema::{check_config_file, config_schema},
+    validation::validate,
+};
+use eyre::{Context, Report, Result};
+use log::{error, info, warn, LevelFilter};
+use pr_bump_lib::{
+    explain_next_version, get_latest_release, get_pulls, get_pulls_in_range, publish_release,
+    publish_sticky_comment, stage_cargo_workspace_update, stage_changelog_update,
+    stage_file_field_update, stage_file_update, FileChanges, GitHub, GitHubOperations,
+    GitRepository, PullRequest,
+};
 use std::convert::TryFrom;
-use std::io::Write;
+use std::{env, fs, io::Write, path::PathBuf, process};
 
-use crate::actions_tools::set_output;
+use crate::actions_tools::{append_step_summary, export_variable, set_output};
+use crate::failure::Failure;
+use crate::summary::{render_preview_comment, render_summary};
 
 mod actions_tools;
+mod cli;
 mod config;
+mod failure;
+mod summary;
+
+/// Identifies the comment with the preview of the next version on pull requests
+const PREVIEW_MARKER: &str = "<!-- pr-bump-preview -->";
 
 fn setup_logging() {
     env_logger::Builder::from_default_env()
@@ -24,82 +44,472 @@ fn setup_logging() {
         .init();
 }
 
-async fn run_action() -> Result<()> {
-    group_lines("⚙️  Reading input configuration");
-    let action_config = ActionConfig::try_from_env()?;
+/// Report an error with its full cause chain as an annotation on the workflow run
+///
+/// When the action should not fail on errors, the annotation is a warning instead. Outside of the
+/// action, the error is printed as is.
+///
+/// The error usually interrupts a group of log lines, which is closed first so the error is not
+/// folded away in it.
+fn report_error(e: &Report, fail_on_error: bool, annotate: bool) {
+    close_group();
+    let causes: Vec<String> = e.chain().map(|cause| cause.to_string()).collect();
+    if !annotate {
+        eprintln!("💥  {}", causes.join("\n  caused by: "));
+        return;
+    }
+    let message = escape_command_value(&causes.join("\n  caused by: "));
+
+    if fail_on_error {
+        error!("💥  {}", message);
+    } else {
+        warn!("💥  {} (ignored because fail_on_error is false)", message);
+    }
+}
+
+/// Run the command given on the command line, or the action when there is none
+async fn run(cli_args: CliArgs) -> Result<()> {
+    match cli_args.command {
+        Command::Help => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Command::Schema => {
+            let schema = serde_json::to_string_pretty(&config_schema())?;
+            println!("{}", schema);
+            Ok(())
+        }
+        Command::Action => {
+            group_lines("⚙️  Reading input configuration");
+            let action_config = ActionConfig::try_from_env().wrap_err(Failure::Config)?;
+            let pr_bump_config = load_config(&action_config)?;
+            close_group();
+
+            let command = match action_config.mode {
+                Mode::Release => Command::Action,
+                Mode::Preview => Command::Preview,
+                Mode::Check => Command::Check,
+            };
+            run_with_config(&action_config, &pr_bump_config, command).await
+        }
+        command => {
+            group_lines("⚙️  Reading input configuration");
+            let action_config = ActionConfig::try_from_cli(&cli_args).wrap_err(Failure::Config)?;
+            // Without a file, only the defaults would be checked, which are always valid
+            if command == Command::ValidateConfig && action_config.configuration_file.is_none() {
+                return Err(eyre::eyre!(
+                    "validate-config needs a configuration file, give it with --config"
+                ))
+                .wrap_err(Failure::Config);
+            }
+            let pr_bump_config = load_config(&action_config)?;
+            // Parsing first reports errors with a suggestion and a location, the schema comes after
+            if let (Command::ValidateConfig, Some(config)) =
+                (command, &action_config.configuration_file)
+            {
+                check_config_file(config).wrap_err(Failure::Config)?;
+            }
+            close_group();
+
+            if command == Command::ValidateConfig {
+                info!("✅  The configuration is valid");
+                return Ok(());
+            }
+            run_with_config(&action_config, &pr_bump_config, command).await
+        }
+    }
+}
 
+/// Read the configuration file (or the defaults) and validate it
+fn load_config(action_config: &ActionConfig) -> Result<PrBumpConfig> {
     let pr_bump_config = {
-        if let Some(config) = action_config.configuration_file {
-            let file_config = action_config.workspace.join(config);
-            PrBumpConfig::try_from(file_config.as_ref())?.merge(PrBumpConfig::default())
-        } else {
-            PrBumpConfig::default()
+        let file_config = match &action_config.configuration_file {
+            Some(config) => {
+                Some(PrBumpConfig::try_from(config.as_path()).wrap_err(Failure::Config)?)
+            }
+            None => {
+                let manifest = action_config.workspace.join("Cargo.toml");
+                PrBumpConfig::from_cargo_metadata(&manifest).wrap_err(Failure::Config)?
+            }
+        };
+
+        match file_config {
+            Some(config) => config.merge(PrBumpConfig::default()),
+            None => PrBumpConfig::default(),
         }
     };
+
+    let ignored_keys = pr_bump_config.changelog_builder_keys();
+    if !ignored_keys.is_empty() {
+        info!(
+            "Ignoring release-changelog-builder keys: {}",
+            ignored_keys.join(", ")
+        );
+    }
+
+    let config_source = match &action_config.configuration_file {
+        Some(config) => fs::read_to_string(config)
+            .ok()
+            .map(|contents| (config.clone(), contents)),
+        None => None,
+    };
+    validate(
+        &pr_bump_config,
+        &action_config.workspace,
+        config_source
+            .as_ref()
+            .map(|(path, contents)| (path.as_path(), contents.as_str())),
+    )
+    .wrap_err(Failure::Config)?;
+
+    Ok(pr_bump_config)
+}
+
+/// Make sure a pull request has a label that decides what happens to it, so it is not left out of a
+/// version bump by mistake
+fn check_pull_labels(event: &PullRequestEvent, pr_bump_config: &PrBumpConfig) -> Result<()> {
+    group_lines("🏷️  Checking the pull request's labels");
+    let accepted = pr_bump_config.accepted_labels();
+    let matching: Vec<&str> = event
+        .pull
+        .labels
+        .iter()
+        .map(String::as_str)
+        .filter(|label| accepted.contains(label))
+        .collect();
+
+    if matching.is_empty() {
+        close_group();
+        let found = match event.pull.labels.is_empty() {
+            true => "it has no labels".to_string(),
+            false => format!("it has {}", quoted(&event.pull.labels)),
+        };
+        return Err(eyre::eyre!(
+            "#{} needs one of the labels {}, but {}",
+            event.pull.number,
+            quoted(&accepted),
+            found
+        ))
+        .wrap_err(Failure::Label);
+    }
+
+    info!(
+        "✅ #{} has the label(s) {}",
+        event.pull.number,
+        quoted(&matching)
+    );
     close_group();
+    Ok(())
+}
+
+/// List labels as `'a', 'b', 'c'`
+fn quoted(labels: &[impl AsRef<str>]) -> String {
+    labels
+        .iter()
+        .map(|label| format!("'{}'", label.as_ref()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-    let github = GitHub::new(
-        &action_config.repo.owner,
-        &action_config.repo.repo,
-        action_config.github_token,
-    )?;
+async fn run_with_config(
+    action_config: &ActionConfig,
+    pr_bump_config: &PrBumpConfig,
+    command: Command,
+) -> Result<()> {
+    if command == Command::Check {
+        let event = PullRequestEvent::try_from_env().wrap_err(Failure::Config)?;
+        return check_pull_labels(&event, pr_bump_config);
+    }
+
+    match action_config.backend {
+        Backend::GitHub => {
+            let repo = action_config
+                .repo
+                .as_ref()
+                .ok_or_else(|| {
+                    eyre::eyre!(
+                        "The github backend needs the repository, from --repo or GITHUB_REPOSITORY"
+                    )
+                })
+                .wrap_err(Failure::Config)?;
+            let github = GitHub::new(&repo.owner, &repo.repo, action_config.github_token.clone())
+                .wrap_err(Failure::GitHub)?;
+            run_with_backend(&github, action_config, pr_bump_config, command).await
+        }
+        Backend::Git => {
+            // Refused before any file is written, so nothing is left half published
+            if command == Command::Action && pr_bump_config.publish_release.is_some() {
+                return Err(eyre::eyre!(
+                    "publish_release needs the github backend, releases cannot be published from git history"
+                ))
+                .wrap_err(Failure::Config);
+            }
+
+            let repository = GitRepository::new(&action_config.workspace);
+            run_with_backend(&repository, action_config, pr_bump_config, command).await
+        }
+    }
+}
+
+async fn run_with_backend<Operations, PRs>(
+    github: &Operations,
+    action_config: &ActionConfig,
+    pr_bump_config: &PrBumpConfig,
+    command: Command,
+) -> Result<()>
+where
+    Operations: GitHubOperations<PullIter = PRs>,
+    PRs: Iterator<Item = PullRequest>,
+{
+    let event = match command {
+        Command::Preview => Some(PullRequestEvent::try_from_env().wrap_err(Failure::Config)?),
+        _ => None,
+    };
+
+    // A preview is for the branch the pull request would be merged into, up to its current commit
+    let (branch, head) = match &event {
+        Some(event) => (Some(event.base_ref.as_str()), Some(&event.base_sha)),
+        None => (action_config.branch.as_deref(), action_config.sha.as_ref()),
+    };
+    let bump_rules = pr_bump_config
+        .get_bump_rules(branch)
+        .wrap_err(Failure::Config)?;
 
     group_lines("🛳️  Finding latest release");
-    let latest = get_latest_release(&github).await?;
+    // With prerelease channels, a prerelease graduates on a branch without a channel, so the
+    // latest release includes prereleases on every branch
+    let include_prereleases = pr_bump_config
+        .prerelease_channels
+        .as_ref()
+        .is_some_and(|channels| !channels.is_empty());
+    let latest = get_latest_release(github, include_prereleases)
+        .await
+        .wrap_err(Failure::GitHub)?;
+    let current_version = latest.get_version().wrap_err(Failure::Version)?;
     close_group();
 
     group_lines("📜  Reading pull requests");
-    let ignored_labels = pr_bump_config.ignore_labels.clone();
-    let pulls = get_pulls(
-        &github,
-        pr_bump_config.base_branches.as_ref(),
-        ignored_labels.unwrap_or_default(),
-        &latest.created_at,
-    )
-    .await?;
+    let pulls = match (pr_bump_config.pull_selection, head) {
+        (Some(PullSelection::CommitRange), Some(sha)) => get_pulls_in_range(github, &latest, sha)
+            .await
+            .wrap_err(Failure::GitHub)?,
+        (Some(PullSelection::CommitRange), None) => {
+            return Err(eyre::eyre!(
+                "GITHUB_SHA is not set, it is needed to select pull requests from the commit range"
+            ))
+            .wrap_err(Failure::Config);
+        }
+        _ => get_pulls(
+            github,
+            pr_bump_config.base_branches.as_ref(),
+            &latest.created_at,
+        )
+        .await
+        .wrap_err(Failure::GitHub)?,
+    };
+    let mut pulls: Vec<PullRequest> = pulls.collect();
+    if let Some(event) = &event {
+        if pulls.iter().all(|pull| pull.number != event.pull.number) {
+            info!(
+                "🔮 #{} - {} (previewed)",
+                event.pull.number, event.pull.title
+            );
+            pulls.push(event.pull.clone());
+        }
+    }
     close_group();
 
     group_lines("🎯  Calculating version bump");
-    let next_version = get_next_version(
-        &latest.get_version()?,
-        &pr_bump_config.get_bump_rules(),
-        pulls,
-    );
+    let decision = explain_next_version(&current_version, &bump_rules, pulls.clone().into_iter());
+    let next_version = decision.next_version.clone();
+    info!("{}", decision.render_table());
     close_group();
 
+    match command {
+        Command::NextVersion => {
+            println!("{}", next_version);
+            return Ok(());
+        }
+        Command::Explain => {
+            println!("Latest release: {}\n", latest.tag_name);
+            print!("{}", decision.render_table());
+            return Ok(());
+        }
+        Command::Preview => {
+            let event = event.expect("The event is read for previews");
+            let comment =
+                render_preview_comment(&decision, event.pull.number, action_config.repo.as_ref());
+
+            group_lines("🔮  Commenting the next version on the pull request");
+            if action_config.dry_run {
+                info!(
+                    "Dry run, not commenting on #{}:\n{}",
+                    event.pull.number, comment
+                );
+            } else {
+                publish_sticky_comment(github, event.pull.number, PREVIEW_MARKER, &comment)
+                    .await
+                    .wrap_err(Failure::GitHub)?;
+            }
+            close_group();
+            return Ok(());
+        }
+        _ => {}
+    }
+
     group_lines("✏️  Updating files with the new version");
-    for bump_file in &pr_bump_config.bump_files.unwrap() {
-        let full_path = &action_config.workspace.join(&bump_file.path);
+    let mut changes = FileChanges::new();
+    let mut matches: Vec<(PathBuf, usize)> = Vec::new();
+    for bump_file in pr_bump_config.bump_files.as_ref().unwrap() {
+        let template = bump_file.template().wrap_err(Failure::Config)?;
+
+        for full_path in bump_file
+            .paths(&action_config.workspace)
+            .wrap_err(Failure::File)?
+        {
+            let count = match bump_file.format {
+                Some(format) => stage_file_field_update(
+                    &current_version,
+                    &next_version,
+                    format,
+                    bump_file
+                        .key_path
+                        .as_deref()
+                        .unwrap_or_else(|| format.default_key_path()),
+                    &template,
+                    &full_path,
+                    &mut changes,
+                )
+                // A field holds a single version
+                .map(|()| 1),
+                None => stage_file_update(
+                    &current_version,
+                    &next_version,
+                    &bump_file.prefix,
+                    &template,
+                    bump_file.occurrences,
+                    &full_path,
+                    &mut changes,
+                ),
+            }
+            .wrap_err(Failure::File)?;
+
+            let relative_path = full_path
+                .strip_prefix(&action_config.workspace)
+                .unwrap_or(&full_path)
+                .to_path_buf();
+            match matches.iter_mut().find(|(path, _)| *path == relative_path) {
+                Some((_, total)) => *total += count,
+                None => matches.push((relative_path, count)),
+            }
+        }
+    }
+
+    if !matches.is_empty() {
+        info!("Versions bumped per file:");
+        for (path, count) in matches {
+            info!("  {}: {}", path.to_string_lossy(), count);
+        }
+    }
 
-        update_file(
-            &latest.get_version()?,
-            &next_version,
-            &bump_file.prefix,
-            &full_path,
-        )?;
+    if let Some(cargo_workspace) = &pr_bump_config.cargo_workspace {
+        let full_path = action_config.workspace.join(&cargo_workspace.path);
+        stage_cargo_workspace_update(&current_version, &next_version, &full_path, &mut changes)
+            .wrap_err(Failure::File)?;
     }
     close_group();
 
-    if latest.get_version().unwrap() == next_version {
+    let has_bump = current_version != next_version;
+    let included: Vec<PullRequest> = pulls
+        .into_iter()
+        .filter(|pull| !bump_rules.pull_filter().is_ignored(pull))
+        .collect();
+    let changelog = pr_bump_config.get_changelog().render(&included);
+    if let (true, Some(changelog_file)) = (has_bump, &pr_bump_config.changelog_file) {
+        group_lines("📰  Updating the changelog");
+        let full_path = action_config.workspace.join(&changelog_file.path);
+        let tag_name = pr_bump_config.tag_name(&next_version);
+        stage_changelog_update(&tag_name, &changelog, &full_path, &mut changes)
+            .wrap_err(Failure::File)?;
+        close_group();
+    }
+
+    let diff = changes.unified_diff(&action_config.workspace);
+    if action_config.dry_run {
+        group_lines("🔍  Dry run, showing changes instead of writing them");
+        info!("{}", diff);
+        close_group();
+    } else {
+        group_lines("💾  Writing files");
+        changes.write().wrap_err(Failure::File)?;
+        close_group();
+    }
+
+    let publish_release_config = match command {
+        Command::Action => pr_bump_config.publish_release.as_ref(),
+        _ => None,
+    };
+    if let (true, Some(publish)) = (has_bump, publish_release_config) {
+        group_lines("🚀  Publishing release");
+        let release = publish.new_release(&next_version, action_config.sha.clone(), &changelog);
+        if action_config.dry_run {
+            info!("Dry run, not publishing release '{}'", release.tag_name);
+        } else {
+            publish_release(github, &release)
+                .await
+                .wrap_err(Failure::GitHub)?;
+        }
+        close_group();
+    }
+
+    if !has_bump {
         info!(
             "✅ Done! Version did not change (current: {})",
             &next_version
         );
-        set_output("has_bump", "false");
     } else {
         info!(
             "✅ Done! Performed a version bump: {} ➡ {}",
-            &latest.get_version().unwrap(),
-            &next_version
+            &current_version, &next_version
         );
-        set_output("has_bump", "true");
     }
 
-    set_output(
-        "previous_version",
-        &latest.get_version().unwrap().to_string(),
+    // Outputs, variables and the summary are only for workflow runs
+    if command != Command::Action {
+        return Ok(());
+    }
+
+    let files: Vec<String> = changes
+        .modifications()
+        .map(|change| {
+            change
+                .path
+                .strip_prefix(&action_config.workspace)
+                .unwrap_or(&change.path)
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    let summary = render_summary(
+        &decision,
+        action_config.repo.as_ref(),
+        &files,
+        action_config.dry_run,
     );
-    set_output("next_version", &next_version.to_string());
+    append_step_summary(&summary).wrap_err(Failure::File)?;
+
+    set_output("has_bump", &has_bump.to_string()).wrap_err(Failure::File)?;
+
+    let previous_version = current_version.to_string();
+    set_output("previous_version", &previous_version).wrap_err(Failure::File)?;
+    set_output("next_version", &next_version.to_string()).wrap_err(Failure::File)?;
+    set_output("changelog", &changelog).wrap_err(Failure::File)?;
+    set_output("diff", &diff).wrap_err(Failure::File)?;
+    let decision = serde_json::to_string(&decision).wrap_err("Could not serialize the decision")?;
+    set_output("decision", &decision).wrap_err(Failure::File)?;
+
+    export_variable("PR_BUMP_PREVIOUS_VERSION", &previous_version).wrap_err(Failure::File)?;
+    export_variable("PR_BUMP_NEXT_VERSION", &next_version.to_string()).wrap_err(Failure::File)?;
 
     Ok(())
 }
@@ -111,9 +521,19 @@ async fn main() {
 
     setup_logging();
 
-    let result = run_action().await;
-    match result {
-        Ok(_) => {}
-        Err(e) => error!("💥  {}", e),
+    let (result, in_action) = match CliArgs::parse(env::args().skip(1)) {
+        Ok(cli_args) => {
+            let in_action = cli_args.command == Command::Action;
+            (run(cli_args).await, in_action)
+        }
+        Err(e) => (Err(e.wrap_err(Failure::Config)), false),
+    };
+    if let Err(e) = result {
+        let fail_on_error = !in_action || fail_on_error_from_env();
+        report_error(&e, fail_on_error, in_action);
+
+        if fail_on_error {
+            process::exit(Failure::of(&e).map_or(1, |failure| failure.exit_code()));
+        }
     }
 }
@@ -0,0 +1,150 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::PullRequest;
+
+type Label = String;
+
+/// When the labels on a pull request make it ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoreMode {
+    /// Any of its labels is ignored
+    #[default]
+    Any,
+    /// All of its labels are ignored
+    All,
+}
+
+/// Decides which pull requests are ignored, so every backend ignores the same pull requests
+///
+/// Ignored pull requests do not bump the version and are left out of the changelog. Pull requests
+/// without labels are never ignored because of `ignored_labels` (whatever the mode), they are only
+/// ignored when unlabeled pull requests are not included.
+#[derive(Debug, Clone)]
+pub struct PullFilter {
+    ignored_labels: Vec<Label>,
+    mode: IgnoreMode,
+    include_unlabeled: bool,
+}
+
+impl Default for PullFilter {
+    fn default() -> Self {
+        PullFilter {
+            ignored_labels: Vec::new(),
+            mode: IgnoreMode::default(),
+            include_unlabeled: true,
+        }
+    }
+}
+
+impl PullFilter {
+    pub fn new(ignored_labels: Vec<Label>, mode: IgnoreMode, include_unlabeled: bool) -> Self {
+        PullFilter {
+            ignored_labels,
+            mode,
+            include_unlabeled,
+        }
+    }
+
+    /// Why a pull request is ignored, or `None` when it is not
+    pub fn ignore_reason(&self, pr: &PullRequest) -> Option<String> {
+        if pr.labels.is_empty() {
+            return match self.include_unlabeled {
+                true => None,
+                false => Some("unlabeled pull requests are ignored".to_string()),
+            };
+        }
+
+        let ignored: Vec<&Label> = pr
+            .labels
+            .iter()
+            .filter(|label| self.ignored_labels.contains(label))
+            .collect();
+        match self.mode {
+            IgnoreMode::Any if !ignored.is_empty() => {
+                Some(format!("ignored label '{}'", ignored[0]))
+            }
+            IgnoreMode::All if ignored.len() == pr.labels.len() => Some(format!(
+                "every label is ignored ({})",
+                ignored
+                    .iter()
+                    .map(|label| format!("'{}'", label))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn is_ignored(&self, pr: &PullRequest) -> bool {
+        self.ignore_reason(pr).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pull(labels: &[&str]) -> PullRequest {
+        let labels = labels.iter().map(|label| label.to_string()).collect();
+        PullRequest::new(1, "Title".to_string(), None, labels, None)
+    }
+
+    fn filter(mode: IgnoreMode, include_unlabeled: bool) -> PullFilter {
+        PullFilter::new(
+            vec!["skip".to_string(), "chore".to_string()],
+            mode,
+            include_unlabeled,
+        )
+    }
+
+    #[test]
+    fn any_mode_ignores_a_pull_with_one_ignored_label() {
+        let filter = filter(IgnoreMode::Any, true);
+
+        assert_eq!(
+            filter.ignore_reason(&pull(&["feat", "skip"])),
+            Some("ignored label 'skip'".to_string())
+        );
+        assert!(filter.is_ignored(&pull(&["skip", "chore"])));
+        assert!(!filter.is_ignored(&pull(&["feat"])));
+    }
+
+    #[test]
+    fn all_mode_ignores_a_pull_only_when_every_label_is_ignored() {
+        let filter = filter(IgnoreMode::All, true);
+
+        assert!(!filter.is_ignored(&pull(&["feat", "skip"])));
+        assert_eq!(
+            filter.ignore_reason(&pull(&["skip", "chore"])),
+            Some("every label is ignored ('skip', 'chore')".to_string())
+        );
+        assert!(!filter.is_ignored(&pull(&["feat"])));
+    }
+
+    #[test]
+    fn unlabeled_pulls_are_included_by_default() {
+        assert!(!PullFilter::default().is_ignored(&pull(&[])));
+        assert!(!filter(IgnoreMode::Any, true).is_ignored(&pull(&[])));
+    }
+
+    #[test]
+    fn unlabeled_pulls_are_ignored_when_not_included() {
+        let filter = filter(IgnoreMode::Any, false);
+
+        assert_eq!(
+            filter.ignore_reason(&pull(&[])),
+            Some("unlabeled pull requests are ignored".to_string())
+        );
+        assert!(!filter.is_ignored(&pull(&["feat"])));
+    }
+
+    #[test]
+    fn ignored_labels_do_not_ignore_unlabeled_pulls() {
+        // With no labels, every label is trivially ignored in `all` mode, which must not count
+        for mode in [IgnoreMode::Any, IgnoreMode::All] {
+            assert!(!filter(mode, true).is_ignored(&pull(&[])));
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use pr_bump_lib::{BumpDecision, PullDecision};
+use std::env;
+
+use crate::config::actions_config::Repo;
+
+/// Render the release plan as Markdown for the job summary
+///
+/// Pull requests link to their page, either from the backend or from the repository.
+pub fn render_summary(
+    decision: &BumpDecision,
+    repo: Option<&Repo>,
+    files: &[String],
+    dry_run: bool,
+) -> String {
+    let level = decision
+        .level
+        .map_or("none".to_string(), |level| level.to_string());
+    let mut summary = format!(
+        "## 🛳️ Release plan\n\n\
+         | Previous version | Next version | Bump |\n\
+         |------------------|--------------|------|\n\
+         | {} | {} | {} |\n",
+        decision.current_version, decision.next_version, level
+    );
+    for note in &decision.notes {
+        summary.push_str(&format!("\n> {}\n", note));
+    }
+    summary.push_str(&render_pulls(decision, repo));
+
+    if !files.is_empty() {
+        summary.push_str(match dry_run {
+            true => "\n### Files to update (dry run)\n\n",
+            false => "\n### Files updated\n\n",
+        });
+        for file in files {
+            summary.push_str(&format!("- `{}`\n", file));
+        }
+    }
+
+    summary
+}
+
+/// Render the next version as Markdown for a comment on a pull request that is not merged yet
+pub fn render_preview_comment(
+    decision: &BumpDecision,
+    pull_number: u64,
+    repo: Option<&Repo>,
+) -> String {
+    let mut comment = "### 🔮 Version preview\n\n".to_string();
+    match decision.level {
+        Some(level) if decision.next_version != decision.current_version => {
+            comment.push_str(&format!(
+                "If this pull request is merged, the next version will be **{}** (a {} bump from {}).\n",
+                decision.next_version, level, decision.current_version
+            ))
+        }
+        _ => comment.push_str(&format!(
+            "If this pull request is merged, the version will stay **{}**.\n",
+            decision.current_version
+        )),
+    }
+
+    match decision
+        .pulls
+        .iter()
+        .find(|pull| pull.number == pull_number)
+    {
+        Some(pull) => match pull.level {
+            Some(level) => {
+                let because: Vec<String> =
+                    pull.contributions.iter().map(|c| c.to_string()).collect();
+                comment.push_str(&format!(
+                    "\nThis pull request asks for a {} bump because of {}.\n",
+                    level,
+                    because.join(", ")
+                ));
+            }
+            None => comment.push_str(&format!(
+                "\nThis pull request does not ask for a bump ({}).\n",
+                pull.ignored_because.as_deref().unwrap_or_default()
+            )),
+        },
+        None => comment.push_str("\nThis pull request is ignored because of its labels.\n"),
+    }
+
+    for note in &decision.notes {
+        comment.push_str(&format!("\n> {}\n", note));
+    }
+    comment.push_str(&render_pulls(decision, repo));
+
+    comment
+}
+
+/// Render the pull requests that ask for a bump and those that do not, as Markdown tables
+fn render_pulls(decision: &BumpDecision, repo: Option<&Repo>) -> String {
+    let mut summary = String::new();
+    let contributing: Vec<&PullDecision> = decision.contributing().collect();
+    if !contributing.is_empty() {
+        summary.push_str(
+            "\n### Contributing pull requests\n\n\
+             | Pull request | Level | Because |\n\
+             |--------------|-------|---------|\n",
+        );
+        for pull in contributing {
+            let because: Vec<String> = pull.contributions.iter().map(|c| c.to_string()).collect();
+            summary.push_str(&format!(
+                "| {} | {} | {} |\n",
+                pull_link(pull, repo),
+                pull.level
+                    .map_or("-".to_string(), |level| level.to_string()),
+                escape_cell(&because.join(", "))
+            ));
+        }
+    }
+
+    let ignored: Vec<&PullDecision> = decision.ignored().collect();
+    if !ignored.is_empty() {
+        summary.push_str(
+            "\n### Ignored pull requests\n\n\
+             | Pull request | Reason |\n\
+             |--------------|--------|\n",
+        );
+        for pull in ignored {
+            summary.push_str(&format!(
+                "| {} | {} |\n",
+                pull_link(pull, repo),
+                escape_cell(pull.ignored_because.as_deref().unwrap_or_default())
+            ));
+        }
+    }
+
+    summary
+}
+
+fn pull_link(pull: &PullDecision, repo: Option<&Repo>) -> String {
+    let url = pull.url.clone().or_else(|| {
+        let server =
+            env::var("GITHUB_SERVER_URL").unwrap_or_else(|_| "https://github.com".to_string());
+        repo.map(|repo| {
+            format!(
+                "{}/{}/{}/pull/{}",
+                server, repo.owner, repo.repo, pull.number
+            )
+        })
+    });
+
+    let title = escape_cell(&pull.title);
+    match url {
+        Some(url) => format!("[#{}]({}) {}", pull.number, url, title),
+        None => format!("#{} {}", pull.number, title),
+    }
+}
+
+/// Keep a value from breaking out of its Markdown table cell
+fn escape_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
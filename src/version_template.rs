@@ -0,0 +1,152 @@
+use eyre::{eyre, Result};
+use semver::Version;
+
+/// How a version is written in a file, like `v{version}` or `{major}.{minor}`
+///
+/// The placeholders are `{version}`, `{major}`, `{minor}`, `{patch}`, `{pre}` and `{build}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionTemplate {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Version,
+    Major,
+    Minor,
+    Patch,
+    Pre,
+    Build,
+}
+
+impl VersionTemplate {
+    pub fn new(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| eyre!("Unclosed placeholder in template '{}'", template))?;
+            let part = match &rest[start + 1..start + end] {
+                "version" => Part::Version,
+                "major" => Part::Major,
+                "minor" => Part::Minor,
+                "patch" => Part::Patch,
+                "pre" => Part::Pre,
+                "build" => Part::Build,
+                other => {
+                    return Err(eyre!(
+                        "Unknown placeholder '{{{}}}' in template '{}'",
+                        other,
+                        template
+                    ))
+                }
+            };
+            parts.push(part);
+            rest = &rest[start + end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        if !parts.iter().any(|part| !matches!(part, Part::Literal(_))) {
+            return Err(eyre!("Template '{}' has no placeholder", template));
+        }
+
+        Ok(VersionTemplate { parts })
+    }
+
+    /// Write a version the way the template describes it
+    pub fn render(&self, version: &Version) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => literal.clone(),
+                Part::Version => version.to_string(),
+                Part::Major => version.major.to_string(),
+                Part::Minor => version.minor.to_string(),
+                Part::Patch => version.patch.to_string(),
+                Part::Pre => version.pre.to_string(),
+                Part::Build => version.build.to_string(),
+            })
+            .collect()
+    }
+
+    /// A regex matching any version written with the template
+    pub(crate) fn pattern(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Literal(literal) => regex::escape(literal),
+                Part::Version => {
+                    r"\d+\.\d+\.\d+(?:-[0-9A-Za-z.-]+)?(?:\+[0-9A-Za-z.-]+)?".to_string()
+                }
+                Part::Major | Part::Minor | Part::Patch => r"\d+".to_string(),
+                Part::Pre | Part::Build => r"[0-9A-Za-z.-]*".to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Default for VersionTemplate {
+    fn default() -> Self {
+        VersionTemplate {
+            parts: vec![Part::Version],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn templates_render_every_placeholder() {
+        let version = Version::parse("1.2.3-beta.1+build.5").unwrap();
+        let render = |template: &str| VersionTemplate::new(template).unwrap().render(&version);
+
+        assert_eq!(render("v{version}"), "v1.2.3-beta.1+build.5");
+        assert_eq!(render("{major}.{minor}"), "1.2");
+        assert_eq!(render("{patch}-{pre}-{build}"), "3-beta.1-build.5");
+        assert_eq!(
+            VersionTemplate::default().render(&version),
+            version.to_string()
+        );
+    }
+
+    #[test]
+    fn invalid_templates_are_refused() {
+        for (template, error) in [
+            ("v{version", "Unclosed placeholder in template 'v{version'"),
+            (
+                "{minor}.{path}",
+                "Unknown placeholder '{path}' in template '{minor}.{path}'",
+            ),
+            ("latest", "Template 'latest' has no placeholder"),
+        ] {
+            assert_eq!(
+                VersionTemplate::new(template).unwrap_err().to_string(),
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn the_pattern_matches_rendered_versions_only() {
+        let template = VersionTemplate::new("v{major}.{minor}").unwrap();
+        let pattern = Regex::new(&format!("^{}$", template.pattern())).unwrap();
+
+        assert!(pattern.is_match("v1.2"));
+        assert!(pattern.is_match("v10.20"));
+        assert!(!pattern.is_match("v1.2.3"));
+        assert!(!pattern.is_match("1.2"));
+    }
+}